@@ -13,11 +13,11 @@ fn count_increasings(input: &[i32], window_size: usize) -> usize {
 }
 
 pub fn part1() -> usize {
-    count_increasings(&*INPUT, 1)
+    count_increasings(&INPUT, 1)
 }
 
 pub fn part2() -> usize {
-    count_increasings(&*INPUT, 3)
+    count_increasings(&INPUT, 3)
 }
 
 #[cfg(test)]
@@ -75,12 +75,12 @@ fn compute_position_with_aim(commands: &[Command]) -> (usize, usize) {
 }
 
 pub fn part1() -> usize {
-    let (x, y) = compute_position(&*INPUT);
+    let (x, y) = compute_position(&INPUT);
     x * y
 }
 
 pub fn part2() -> usize {
-    let (x, y) = compute_position_with_aim(&*INPUT);
+    let (x, y) = compute_position_with_aim(&INPUT);
     x * y
 }
 
@@ -119,11 +119,11 @@ fn get_bit_pattern(input: &[i32], nbits: usize, raw: bool) -> Vec<i32> {
 }
 
 pub fn part1() -> usize {
-    power_consumption(&*INPUT, 12)
+    power_consumption(&INPUT, 12)
 }
 
 pub fn part2() -> usize {
-    life_support(&*INPUT, 12)
+    life_support(&INPUT, 12)
 }
 
 #[cfg(test)]
@@ -130,11 +130,11 @@ fn sum_unmarked_numbers(board: &Board) -> usize {
 }
 
 pub fn part1() -> usize {
-    find_first_winning_board(&mut BOARDS.to_vec(), &*CHOSEN_NUMBERS)
+    find_first_winning_board(&mut BOARDS.to_vec(), &CHOSEN_NUMBERS)
 }
 
 pub fn part2() -> usize {
-    find_last_winning_board(&BOARDS.to_vec(), &*CHOSEN_NUMBERS)
+    find_last_winning_board(&BOARDS.to_vec(), &CHOSEN_NUMBERS)
 }
 
 #[cfg(test)]
@@ -244,6 +244,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_to_owned)]
     fn part2_example() {
         assert_eq!(
             find_last_winning_board(&BOARDS.to_vec(), CHOSEN_NUMBERS),
@@ -19,7 +19,7 @@ struct Coordinate {
 
 fn points_least_two_overlap(
     lines: &[Line],
-    mut update_map: impl FnMut(&mut Vec<Vec<i32>>, &Line),
+    mut update_map: impl FnMut(&mut [Vec<i32>], &Line),
     filter: impl Fn(&Line) -> bool,
 ) -> usize {
     let mut map = vec![vec![0; 1000]; 1000];
@@ -55,7 +55,7 @@ fn parse_line(line: &str) -> Line {
     Line { from, to }
 }
 
-fn horizontal_vertical(map: &mut Vec<Vec<i32>>, line: &Line) {
+fn horizontal_vertical(map: &mut [Vec<i32>], line: &Line) {
     let Line { from, to } = line;
     let (mut x1, mut y1) = (from.x as usize, from.y as usize);
     let (x2, y2) = (to.x as usize, to.y as usize);
@@ -84,7 +84,7 @@ fn horizontal_vertical(map: &mut Vec<Vec<i32>>, line: &Line) {
     map[y1][x1] += 1;
 }
 
-fn horizontal_vertical_diagonal(map: &mut Vec<Vec<i32>>, line: &Line) {
+fn horizontal_vertical_diagonal(map: &mut [Vec<i32>], line: &Line) {
     let Line { from, to } = line;
     let (mut x1, mut y1) = (from.x as usize, from.y as usize);
     let (x2, y2) = (to.x as usize, to.y as usize);
@@ -132,7 +132,7 @@ fn horizontal_vertical_diagonal(map: &mut Vec<Vec<i32>>, line: &Line) {
             y1 += 1;
         }
     } else {
-        let down_to = if x1 < x2 { x2 - x1 } else { x1 - x2 };
+        let down_to = x2.abs_diff(x1);
         let xn = if x1 < x2 { 1 } else { -1 };
         let yn = if y1 < y2 { 1 } else { -1 };
 
@@ -164,11 +164,11 @@ fn deny_diagonals(line: &Line) -> bool {
 }
 
 pub fn part1() -> usize {
-    points_least_two_overlap(&*INPUT, horizontal_vertical, deny_diagonals)
+    points_least_two_overlap(&INPUT, horizontal_vertical, deny_diagonals)
 }
 
 pub fn part2() -> usize {
-    points_least_two_overlap(&*INPUT, horizontal_vertical_diagonal, |_| true)
+    points_least_two_overlap(&INPUT, horizontal_vertical_diagonal, |_| true)
 }
 
 #[cfg(test)]
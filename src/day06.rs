@@ -26,11 +26,11 @@ fn number_of_fishes_after_n_days(fish_list: &[u8], days: usize) -> usize {
 }
 
 pub fn part1() -> usize {
-    number_of_fishes_after_n_days(&*INPUT, 80)
+    number_of_fishes_after_n_days(&INPUT, 80)
 }
 
 pub fn part2() -> usize {
-    number_of_fishes_after_n_days(&*INPUT, 256)
+    number_of_fishes_after_n_days(&INPUT, 256)
 }
 
 #[cfg(test)]
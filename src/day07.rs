@@ -10,7 +10,7 @@ fn align_least_fuel(positions: &[usize]) -> usize {
 
     for &pos in positions.iter() {
         for (i, cost) in fuel_cost.iter_mut().enumerate() {
-            *cost += (pos as isize - i as isize).abs() as usize;
+            *cost += (pos as isize - i as isize).unsigned_abs();
         }
     }
 
@@ -42,11 +42,11 @@ fn align_least_fuel2(positions: &[usize]) -> usize {
 }
 
 pub fn part1() -> usize {
-    align_least_fuel(&*INPUT)
+    align_least_fuel(&INPUT)
 }
 
 pub fn part2() -> usize {
-    align_least_fuel2(&*INPUT)
+    align_least_fuel2(&INPUT)
 }
 
 #[cfg(test)]
@@ -1,125 +1,389 @@
-use std::collections::HashMap;
+use std::fmt::{self, Display};
 use std::fs;
+use std::ops::{BitAnd, BitOr, Sub};
+use std::str::FromStr;
+use std::time::Instant;
 
 use once_cell::sync::Lazy;
 
 static FILE: Lazy<String> = Lazy::new(|| fs::read_to_string(INPUT_PATH).unwrap());
-static INPUT: Lazy<Digits<'_>> = Lazy::new(|| {
+static INPUT: Lazy<Digits> = Lazy::new(|| {
     let contents = FILE.lines().collect::<Vec<&str>>();
-    parse_input(&contents)
+    parse_input(&contents).unwrap()
 });
 const INPUT_PATH: &str = "input/day08";
 
-type Digits<'a> = Vec<(Vec<&'a str>, Vec<&'a str>)>;
+type Digits = Vec<(Vec<Segments>, Vec<Segments>)>;
+type StrDigits<'a> = Vec<(Vec<&'a str>, Vec<&'a str>)>;
 
-fn unscramble_numbers(mut digits: Digits<'_>) -> usize {
-    let mut sum = 0;
-    let mut map = HashMap::with_capacity(7);
+// A set of wires or segments; bit 0 is 'a', bit 6 is 'g'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Segments(u8);
 
-    for (observed, output) in &mut digits {
-        observed.sort_unstable_by_key(|o| o.len());
+impl Segments {
+    const ALL: Segments = Segments(0b1111111);
 
-        let one = observed[0];
-        let four = observed[2];
-        let seven = observed[1];
-        let eight = observed[9];
+    fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    fn contains(self, segment: usize) -> bool {
+        self.0 & 1 << segment != 0
+    }
+
+    fn iter(self) -> impl Iterator<Item = usize> {
+        (0..7).filter(move |&segment| self.contains(segment))
+    }
+}
+
+impl BitOr for Segments {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Segments(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Segments {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Segments(self.0 & rhs.0)
+    }
+}
+
+impl Sub for Segments {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Segments(self.0 & !rhs.0)
+    }
+}
 
-        let to_a = diff(seven, one).unwrap();
-        map.insert(to_a, 'a');
+impl FromStr for Segments {
+    type Err = &'static str;
 
-        let four_and_seven = merge(four, seven);
-        let mut zero_six_nine = Vec::from(&observed[6..9]);
-        let mut to_g;
-        let mut i = 0;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Segments::default();
 
-        // Pick a 6-wire entry; it will be either 0, 6 or 9. Only 9 will have exactly one wire
-        // difference when intersected with 4 merged with 7. The intersection will map to g.
-        loop {
-            to_g = diff(zero_six_nine[i], &four_and_seven);
-            if to_g.is_some() {
-                break;
+        for wire in s.bytes() {
+            if !(b'a'..=b'g').contains(&wire) {
+                return Err("Wires must be between 'a' and 'g'");
             }
-            i += 1;
+            let wire = Segments(1 << (wire - b'a'));
+            if segments & wire == wire {
+                return Err("Wire repeated within a pattern");
+            }
+            segments = segments | wire;
         }
 
-        map.insert(to_g.unwrap(), 'g');
+        Ok(segments)
+    }
+}
 
-        let nine = zero_six_nine.remove(i);
-        let zero_six = zero_six_nine;
+impl Display for Segments {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in self.iter() {
+            write!(f, "{}", (b'a' + segment as u8) as char)?;
+        }
+        Ok(())
+    }
+}
 
-        map.insert(diff(eight, nine).unwrap(), 'e');
+const DIGITS: [Segments; 10] = [
+    Segments(0b1110111),
+    Segments(0b0100100),
+    Segments(0b1011101),
+    Segments(0b1101101),
+    Segments(0b0101110),
+    Segments(0b1101011),
+    Segments(0b1111011),
+    Segments(0b0100101),
+    Segments(0b1111111),
+    Segments(0b1101111),
+];
+
+// The segment driven by each scrambled wire, from 'a' to 'g'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Wiring([usize; 7]);
+
+impl Wiring {
+    fn translate(&self, pattern: Segments) -> Segments {
+        pattern.iter().fold(Segments::default(), |segments, wire| {
+            segments | Segments(1 << self.0[wire])
+        })
+    }
 
-        let c_or_d = diff(zero_six[0], zero_six[1]).unwrap();
-        let c_or_d2 = diff(zero_six[1], zero_six[0]).unwrap();
+    fn digit(&self, pattern: Segments) -> Option<usize> {
+        let segments = self.translate(pattern);
+        DIGITS.iter().position(|&digit| digit == segments)
+    }
+}
 
-        if one.contains(c_or_d) {
-            map.insert(c_or_d, 'c');
-            map.insert(c_or_d2, 'd');
-        } else {
-            map.insert(c_or_d, 'd');
-            map.insert(c_or_d2, 'c');
-        };
+impl Display for Wiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (wire, segment) in ('a'..='g').zip(self.0) {
+            if wire != 'a' {
+                write!(f, " ")?;
+            }
+            write!(f, "{}->{}", wire, (b'a' + segment as u8) as char)?;
+        }
+        Ok(())
+    }
+}
 
-        let to_f = seven.chars().find(|&ch| !map.contains_key(&ch)).unwrap();
-        map.insert(to_f, 'f');
-        let to_b = four.chars().find(|&ch| !map.contains_key(&ch)).unwrap();
-        map.insert(to_b, 'b');
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decoded {
+    wiring: Wiring,
+    output: Vec<Segments>,
+    value: usize,
+}
 
-        sum += decode(&map, output);
-        map.clear();
+// Shows the output as wired on the display, then with the wiring fixed.
+impl Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decoded: Vec<Segments> = self
+            .output
+            .iter()
+            .map(|&pattern| self.wiring.translate(pattern))
+            .collect();
+
+        writeln!(f, "wiring: {}", self.wiring)?;
+        writeln!(f, "scrambled:")?;
+        render_digits(f, &self.output)?;
+        writeln!(f, "decoded: {:04}", self.value)?;
+        render_digits(f, &decoded)
     }
-
-    sum
 }
 
-fn decode(map: &HashMap<char, char>, output: &[&str]) -> usize {
-    let mut num = 0;
+// Draws digits side by side in the puzzle's style, with '.' for unlit segments.
+fn render_digits(f: &mut fmt::Formatter<'_>, digits: &[Segments]) -> fmt::Result {
+    for row in 0..7 {
+        let line: Vec<String> = digits
+            .iter()
+            .map(|&digit| {
+                let glyph = |segment: usize| {
+                    if digit.contains(segment) {
+                        (b'a' + segment as u8) as char
+                    } else {
+                        '.'
+                    }
+                };
+                match row {
+                    // Segments 'a', 'd' and 'g' are horizontal.
+                    0 | 3 | 6 => format!(" {0}{0}{0}{0} ", glyph(row)),
+                    1 | 2 => format!("{}    {}", glyph(1), glyph(2)),
+                    _ => format!("{}    {}", glyph(4), glyph(5)),
+                }
+            })
+            .collect();
+        writeln!(f, "{}", line.join("  ").trim_end())?;
+    }
 
-    for (i, &output) in output.iter().rev().enumerate() {
-        let mut pattern = 0;
-        output
-            .chars()
-            .map(|ch| map[&ch] as u8 - b'a')
-            .for_each(|bit| pattern |= 1 << bit);
+    Ok(())
+}
 
-        let n = match pattern {
-            0b1110111 => 0,
-            0b0100100 => 1,
-            0b1011101 => 2,
-            0b1101101 => 3,
-            0b0101110 => 4,
-            0b1101011 => 5,
-            0b1111011 => 6,
-            0b0100101 => 7,
-            0b1111111 => 8,
-            0b1101111 => 9,
-            _ => unreachable!(),
-        };
+fn decode_entry(observed: &[Segments], output: &[Segments]) -> Result<Decoded, &'static str> {
+    let patterns: Vec<Segments> = observed.iter().chain(output).copied().collect();
+    let wiring = solve_wiring(&patterns)?;
+    let value = output.iter().fold(0, |value, &pattern| {
+        value * 10 + wiring.digit(pattern).unwrap()
+    });
+
+    Ok(Decoded {
+        wiring,
+        output: output.to_vec(),
+        value,
+    })
+}
 
-        num += n * 10usize.pow(i as u32);
+// Every wire in a pattern of length n must drive a segment used by some digit with n segments.
+// The remaining wirings are then searched exhaustively.
+fn solve_wiring(patterns: &[Segments]) -> Result<Wiring, &'static str> {
+    let mut candidates = [Segments::ALL; 7];
+
+    for &pattern in patterns {
+        let possible = DIGITS
+            .iter()
+            .filter(|digit| digit.len() == pattern.len())
+            .fold(Segments::default(), |possible, &digit| possible | digit);
+        for wire in pattern.iter() {
+            candidates[wire] = candidates[wire] & possible;
+        }
     }
 
-    num
+    let mut solutions = Vec::new();
+    search_wiring(
+        &candidates,
+        &mut Wiring([0; 7]),
+        Segments::default(),
+        &mut |wiring| {
+            patterns
+                .iter()
+                .all(|&pattern| wiring.digit(pattern).is_some())
+        },
+        &mut solutions,
+    );
+
+    match *solutions {
+        [wiring] => Ok(wiring),
+        [] => Err("No wiring maps every pattern to a digit"),
+        _ => Err("Patterns match more than one wiring"),
+    }
 }
 
-// Returns character difference if `a` is exactly one char longer than `b`.
-fn diff(a: &str, b: &str) -> Option<char> {
-    let diff: Vec<char> = a.chars().filter(|&ch| !b.contains(ch)).collect();
+// Stops as soon as a second solution is found.
+fn search_wiring(
+    candidates: &[Segments; 7],
+    wiring: &mut Wiring,
+    used: Segments,
+    is_solution: &mut impl FnMut(&Wiring) -> bool,
+    solutions: &mut Vec<Wiring>,
+) {
+    let wire = used.len();
+    if wire == 7 {
+        if is_solution(wiring) {
+            solutions.push(*wiring);
+        }
+        return;
+    }
 
-    match *diff {
-        [d] => Some(d),
-        _ => None,
+    for segment in (candidates[wire] - used).iter() {
+        if solutions.len() < 2 {
+            wiring.0[wire] = segment;
+            search_wiring(
+                candidates,
+                wiring,
+                used | Segments(1 << segment),
+                is_solution,
+                solutions,
+            );
+        }
     }
 }
 
-fn merge(a: &str, b: &str) -> String {
-    let a = a.to_string();
-    let mut b = b.to_string();
-    b.retain(|ch| !a.contains(ch));
-    a + &b
+fn unscramble_numbers(digits: &Digits) -> Result<usize, &'static str> {
+    digits
+        .iter()
+        .map(|(observed, output)| Ok(decode_entry(observed, output)?.value))
+        .sum()
+}
+
+// The string-based decoder `Segments` replaced, kept as a baseline for `bench_part2`.
+mod string_based {
+    use super::StrDigits;
+
+    // Segments lit for each digit; bit 0 is segment 'a', bit 6 is segment 'g'.
+    const DIGITS: [u8; 10] = [
+        0b1110111, 0b0100100, 0b1011101, 0b1101101, 0b0101110, 0b1101011, 0b1111011, 0b0100101,
+        0b1111111, 0b1101111,
+    ];
+
+    // The segment driven by each scrambled wire, from 'a' to 'g'.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Wiring([char; 7]);
+
+    impl Wiring {
+        fn digit(&self, pattern: &str) -> Option<usize> {
+            let mut segments = 0;
+            pattern
+                .chars()
+                .map(|wire| self.0[(wire as u8 - b'a') as usize] as u8 - b'a')
+                .for_each(|bit| segments |= 1 << bit);
+
+            DIGITS.iter().position(|&digit| digit == segments)
+        }
+    }
+
+    pub(super) fn decode_entry(observed: &[&str], output: &[&str]) -> Result<usize, &'static str> {
+        let patterns: Vec<&str> = observed.iter().chain(output).copied().collect();
+        let wiring = solve_wiring(&patterns)?;
+        let value = output.iter().fold(0, |value, pattern| {
+            value * 10 + wiring.digit(pattern).unwrap()
+        });
+
+        Ok(value)
+    }
+
+    // Every wire in a pattern of length n must drive a segment used by some digit with n
+    // segments. The remaining wirings are then searched exhaustively.
+    fn solve_wiring(patterns: &[&str]) -> Result<Wiring, &'static str> {
+        let mut candidates = [0b1111111; 7];
+
+        for pattern in patterns {
+            let mut seen = 0u8;
+            for wire in pattern.bytes() {
+                if !(b'a'..=b'g').contains(&wire) {
+                    return Err("Wires must be between 'a' and 'g'");
+                }
+                if seen & 1 << (wire - b'a') != 0 {
+                    return Err("Wire repeated within a pattern");
+                }
+                seen |= 1 << (wire - b'a');
+            }
+
+            let possible = DIGITS
+                .iter()
+                .filter(|digit| digit.count_ones() as usize == pattern.len())
+                .fold(0, |possible, digit| possible | digit);
+            for wire in pattern.bytes() {
+                candidates[(wire - b'a') as usize] &= possible;
+            }
+        }
+
+        let mut solutions = Vec::new();
+        search_wiring(
+            &candidates,
+            patterns,
+            &mut Wiring([' '; 7]),
+            0,
+            &mut solutions,
+        );
+
+        match *solutions {
+            [wiring] => Ok(wiring),
+            [] => Err("No wiring maps every pattern to a digit"),
+            _ => Err("Patterns match more than one wiring"),
+        }
+    }
+
+    // Stops as soon as a second solution is found.
+    fn search_wiring(
+        candidates: &[u8; 7],
+        patterns: &[&str],
+        wiring: &mut Wiring,
+        used: u8,
+        solutions: &mut Vec<Wiring>,
+    ) {
+        let wire = used.count_ones() as usize;
+        if wire == 7 {
+            if patterns
+                .iter()
+                .all(|pattern| wiring.digit(pattern).is_some())
+            {
+                solutions.push(*wiring);
+            }
+            return;
+        }
+
+        for segment in 0..7 {
+            let bit = 1 << segment;
+            if candidates[wire] & bit != 0 && used & bit == 0 && solutions.len() < 2 {
+                wiring.0[wire] = (b'a' + segment) as char;
+                search_wiring(candidates, patterns, wiring, used | bit, solutions);
+            }
+        }
+    }
+
+    pub(super) fn unscramble_numbers(digits: &StrDigits<'_>) -> Result<usize, &'static str> {
+        digits
+            .iter()
+            .map(|(observed, output)| decode_entry(observed, output))
+            .sum()
+    }
 }
 
-fn unique_num_of_segments(digits: Digits<'_>) -> usize {
+fn unique_num_of_segments(digits: &Digits) -> usize {
     let unique_nums = [2, 3, 4, 7];
 
     digits
@@ -129,7 +393,19 @@ fn unique_num_of_segments(digits: Digits<'_>) -> usize {
         .count()
 }
 
-fn parse_input<'a>(input: &[&'a str]) -> Digits<'a> {
+fn parse_input(input: &[&str]) -> Result<Digits, &'static str> {
+    parse_input_str(input)
+        .into_iter()
+        .map(|(observed, output)| {
+            let observed: Result<Vec<_>, _> =
+                observed.iter().map(|pattern| pattern.parse()).collect();
+            let output: Result<Vec<_>, _> = output.iter().map(|pattern| pattern.parse()).collect();
+            Ok((observed?, output?))
+        })
+        .collect()
+}
+
+fn parse_input_str<'a>(input: &[&'a str]) -> StrDigits<'a> {
     input
         .iter()
         .map(|segment| {
@@ -142,11 +418,51 @@ fn parse_input<'a>(input: &[&'a str]) -> Digits<'a> {
 }
 
 pub fn part1() -> usize {
-    unique_num_of_segments(INPUT.clone())
+    unique_num_of_segments(&INPUT)
 }
 
 pub fn part2() -> usize {
-    unscramble_numbers(INPUT.clone())
+    unscramble_numbers(&INPUT).unwrap()
+}
+
+pub fn bench_part2(rounds: u32) {
+    let contents = FILE.lines().collect::<Vec<&str>>();
+    let strings = parse_input_str(&contents);
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        string_based::unscramble_numbers(&strings).unwrap();
+    }
+    let strings = start.elapsed() / rounds;
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        unscramble_numbers(&INPUT).unwrap();
+    }
+    let segments = start.elapsed() / rounds;
+
+    println!("Day 08 [part 02] strings:  {:?}/iter", strings);
+    println!("Day 08 [part 02] segments: {:?}/iter", segments);
+}
+
+pub fn print_decoded() {
+    for (i, (observed, output)) in INPUT.iter().enumerate() {
+        match decode_entry(observed, output) {
+            Ok(decoded) => println!("Entry {}\n{}", i + 1, decoded),
+            Err(err) => println!("Entry {}: {}\n", i + 1, err),
+        }
+    }
+}
+
+pub fn print_wirings() {
+    for (i, (observed, output)) in INPUT.iter().enumerate() {
+        match decode_entry(observed, output) {
+            Ok(Decoded { wiring, value, .. }) => {
+                println!("Entry {}: {} => {:04}", i + 1, wiring, value)
+            }
+            Err(err) => println!("Entry {}: {}", i + 1, err),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -169,7 +485,7 @@ mod tests {
     #[test]
     fn test_parser() {
         assert_eq!(
-            parse_input(&[INPUT[0]]),
+            parse_input_str(&[INPUT[0]]),
             vec![(
                 vec![
                     "be", "cfbegad", "cbdgef", "fgaecd", "cgeb", "fdcge", "agebfd", "fecdb",
@@ -178,15 +494,104 @@ mod tests {
                 vec!["fdgacbe", "cefdb", "cefbgd", "gcbe"]
             )]
         );
+
+        let (observed, output) = &parse_input(&[INPUT[0]]).unwrap()[0];
+        assert_eq!(observed[0], Segments(0b0010010));
+        assert_eq!(output[3].to_string(), "bceg");
+    }
+
+    #[test]
+    fn segment_sets() {
+        let four: Segments = "bcdf".parse().unwrap();
+        let seven: Segments = "acf".parse().unwrap();
+
+        assert_eq!((four | seven).to_string(), "abcdf");
+        assert_eq!((four & seven).to_string(), "cf");
+        assert_eq!((four - seven).to_string(), "bd");
+        assert_eq!((four - seven).len(), 2);
+        assert_eq!(
+            "abh".parse::<Segments>(),
+            Err("Wires must be between 'a' and 'g'")
+        );
+        assert_eq!(
+            "aab".parse::<Segments>(),
+            Err("Wire repeated within a pattern")
+        );
     }
 
     #[test]
     fn part1_example() {
-        assert_eq!(unique_num_of_segments(parse_input(INPUT)), 26);
+        assert_eq!(unique_num_of_segments(&parse_input(INPUT).unwrap()), 26);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(unscramble_numbers(parse_input(INPUT)), 61229);
+        assert_eq!(unscramble_numbers(&parse_input(INPUT).unwrap()), Ok(61229));
+        assert_eq!(
+            string_based::unscramble_numbers(&parse_input_str(INPUT)),
+            Ok(61229)
+        );
+    }
+
+    #[test]
+    fn recovered_wiring() {
+        let entry = parse_input(&[
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        ])
+        .unwrap();
+        let decoded = decode_entry(&entry[0].0, &entry[0].1).unwrap();
+
+        assert_eq!(decoded.wiring, Wiring([2, 5, 6, 0, 1, 3, 4]));
+        assert_eq!(decoded.value, 5353);
+        assert_eq!(
+            decoded.wiring.to_string(),
+            "a->c b->f c->g d->a e->b f->d g->e"
+        );
+    }
+
+    #[test]
+    fn undecodable_entries() {
+        let ab = "ab".parse().unwrap();
+        let bc = "bc".parse().unwrap();
+
+        // Without the other patterns, "ab" could be wired either way round.
+        assert_eq!(
+            decode_entry(&[ab], &[ab]),
+            Err("Patterns match more than one wiring")
+        );
+        // Only "1" has two segments, so three wires can't all light it.
+        assert_eq!(
+            decode_entry(&[ab, bc], &[ab]),
+            Err("No wiring maps every pattern to a digit")
+        );
+        assert_eq!(
+            string_based::decode_entry(&["ab", "bc"], &["ab"]),
+            Err("No wiring maps every pattern to a digit")
+        );
+        assert_eq!(
+            string_based::decode_entry(&["aab"], &["ab"]),
+            Err("Wire repeated within a pattern")
+        );
+    }
+
+    #[test]
+    fn render_decoded() {
+        let entry = parse_input(&[
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        ])
+        .unwrap();
+        let rendered = decode_entry(&entry[0].0, &entry[0].1).unwrap().to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines.len(), 17);
+        assert_eq!(lines[1], "scrambled:");
+        // "cdfeb" lights wires b, c, d, e and f.
+        assert_eq!(&lines[2][..6], " .... ");
+        assert_eq!(&lines[3][..6], "b    c");
+        assert_eq!(lines[9], "decoded: 5353");
+        assert_eq!(lines[10], " aaaa    aaaa    aaaa    aaaa");
+        assert_eq!(lines[11], "b    .  .    c  b    .  .    c");
+        assert_eq!(lines[14], ".    f  .    f  .    f  .    f");
+        assert_eq!(lines[16], " gggg    gggg    gggg    gggg");
     }
 }
@@ -1,4 +1,6 @@
-use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::io;
 
 use once_cell::sync::Lazy;
 
@@ -11,11 +13,24 @@ static INPUT: Lazy<Vec<Vec<u8>>> = Lazy::new(|| {
 });
 const INPUT_PATH: &str = "input/day09";
 
-#[derive(Clone, Copy)]
-enum Status {
-    Unvisited,
-    Enqueued,
-    Visited,
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Basin {
+    // Lowest location of the basin; the first one in reading order if there's a tie.
+    low_point: (usize, usize),
+    members: Vec<(usize, usize)>,
+}
+
+impl Basin {
+    fn size(&self) -> usize {
+        self.members.len()
+    }
+}
+
+#[derive(Debug)]
+struct BasinMap {
+    // Index into `basins` of the basin each location belongs to, or `None` for height 9.
+    labels: Vec<Vec<Option<usize>>>,
+    basins: Vec<Basin>,
 }
 
 fn risk_level(map: &[Vec<u8>]) -> usize {
@@ -33,61 +48,55 @@ fn risk_level(map: &[Vec<u8>]) -> usize {
 }
 
 fn three_largest_basins(map: &[Vec<u8>]) -> usize {
-    let mut sizes = vec![];
-
-    for (i, j) in find_all_low_risk_level_points(map) {
-        let mut to_visit = VecDeque::new();
-        let mut status = vec![vec![Status::Unvisited; map[0].len()]; map.len()];
-        to_visit.push_back((i, j));
-        status[i][j] = Status::Enqueued;
-
-        let size = basin_size(map, &mut to_visit, &mut status);
-        sizes.push(size);
-    }
+    let mut sizes: Vec<usize> = basin_map(map).basins.iter().map(Basin::size).collect();
 
     sizes.sort_unstable();
     sizes.into_iter().rev().take(3).product()
 }
 
-fn basin_size(
-    map: &[Vec<u8>],
-    to_visit: &mut VecDeque<(usize, usize)>,
-    status: &mut [Vec<Status>],
-) -> usize {
-    let mut size = 0;
-
-    // Do a BFS to determine the size of the basin
-    while let Some((i, j)) = to_visit.pop_front() {
-        let my_level = map[i][j];
+// Basins are the areas delimited by locations of height 9, so every other location gets
+// labelled by a single flood fill. Since the low point is just the lowest location in the
+// basin, plateaus without a strictly lower location still get one.
+fn basin_map(map: &[Vec<u8>]) -> BasinMap {
+    let mut labels = vec![vec![None; map[0].len()]; map.len()];
+    let mut basins = Vec::new();
+    let mut to_visit = Vec::new();
 
-        for (their_level, (i, j)) in get_neighbors(map, (i, j)) {
-            if let Status::Unvisited = status[i][j] {
-                if their_level > my_level && their_level < 9 {
-                    to_visit.push_back((i, j));
-                    status[i][j] = Status::Enqueued;
-                }
+    for i in 0..map.len() {
+        for j in 0..map[0].len() {
+            if map[i][j] == 9 || labels[i][j].is_some() {
+                continue;
             }
-        }
 
-        size += 1;
-        status[i][j] = Status::Visited;
-    }
-
-    size
-}
+            let id = basins.len();
+            let mut basin = Basin {
+                low_point: (i, j),
+                members: Vec::new(),
+            };
+            labels[i][j] = Some(id);
+            to_visit.push((i, j));
 
-fn find_all_low_risk_level_points(map: &[Vec<u8>]) -> Vec<(usize, usize)> {
-    let mut low_risk_level = vec![];
+            while let Some((i, j)) = to_visit.pop() {
+                basin.members.push((i, j));
+                let (low_i, low_j) = basin.low_point;
+                if (map[i][j], i, j) < (map[low_i][low_j], low_i, low_j) {
+                    basin.low_point = (i, j);
+                }
 
-    for i in 0..map.len() {
-        for j in 0..map[0].len() {
-            if is_low_point(map, i, j) {
-                low_risk_level.push((i, j));
+                for (level, (i, j)) in get_neighbors(map, (i, j)) {
+                    if level < 9 && labels[i][j].is_none() {
+                        labels[i][j] = Some(id);
+                        to_visit.push((i, j));
+                    }
+                }
             }
+
+            basin.members.sort_unstable();
+            basins.push(basin);
         }
     }
 
-    low_risk_level
+    BasinMap { labels, basins }
 }
 
 fn is_low_point(map: &[Vec<u8>], i: usize, j: usize) -> bool {
@@ -120,12 +129,119 @@ fn get_neighbors(map: &[Vec<u8>], origin: (usize, usize)) -> Vec<(u8, (usize, us
     neighbors
 }
 
+impl BasinMap {
+    // One character per location, cycling through digits and letters by basin, with `#` for
+    // locations that don't belong to any basin.
+    fn render_labels(&self) -> String {
+        let mut rendered = String::new();
+        for row in &self.labels {
+            for label in row {
+                rendered.push(match label {
+                    Some(id) => char::from_digit((id % 36) as u32, 36).unwrap(),
+                    None => '#',
+                });
+            }
+            rendered.push('\n');
+        }
+
+        rendered
+    }
+}
+
+// Deeper locations are darker shades of blue, locations of height 9 are almost black and low
+// points are red. Locations on the edge of a basin are blended with gold to outline it.
+fn location_color(map: &[Vec<u8>], basin_map: &BasinMap, (i, j): (usize, usize)) -> [u8; 3] {
+    const WALL: [u8; 3] = [20, 20, 20];
+    const LOW_POINT: [u8; 3] = [220, 40, 40];
+    const OUTLINE: [u8; 3] = [255, 200, 0];
+
+    let id = match basin_map.labels[i][j] {
+        Some(id) => id,
+        None => return WALL,
+    };
+    if basin_map.basins[id].low_point == (i, j) {
+        return LOW_POINT;
+    }
+
+    let height = map[i][j];
+    let shade = [height * 28, 80 + height * 20, 140 + height * 14];
+    let on_edge = i == 0
+        || j == 0
+        || i == map.len() - 1
+        || j == map[0].len() - 1
+        || get_neighbors(map, (i, j))
+            .iter()
+            .any(|&(_, (x, y))| basin_map.labels[x][y] != Some(id));
+
+    if on_edge {
+        [0, 1, 2].map(|c| ((shade[c] as u16 + OUTLINE[c] as u16) / 2) as u8)
+    } else {
+        shade
+    }
+}
+
+// Two spaces per location, colored with 24-bit ANSI background colors.
+fn render_terminal(map: &[Vec<u8>], basin_map: &BasinMap) -> String {
+    let mut rendered = String::new();
+
+    for i in 0..map.len() {
+        for j in 0..map[0].len() {
+            let [r, g, b] = location_color(map, basin_map, (i, j));
+            write!(rendered, "\x1b[48;2;{};{};{}m  ", r, g, b).unwrap();
+        }
+        rendered.push_str("\x1b[0m\n");
+    }
+
+    rendered
+}
+
+// Binary PPM where every location is a `scale` x `scale` square.
+fn render_ppm(map: &[Vec<u8>], basin_map: &BasinMap, scale: usize) -> Vec<u8> {
+    let (height, width) = (map.len() * scale, map[0].len() * scale);
+    let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+    for y in 0..height {
+        for x in 0..width {
+            ppm.extend(location_color(map, basin_map, (y / scale, x / scale)));
+        }
+    }
+
+    ppm
+}
+
+pub fn print_heightmap() {
+    print!("{}", render_terminal(&INPUT, &basin_map(&INPUT)));
+}
+
+pub fn export_heightmap(path: &str) -> io::Result<()> {
+    fs::write(path, render_ppm(&INPUT, &basin_map(&INPUT), 4))
+}
+
+pub fn print_basins() {
+    let basin_map = basin_map(&INPUT);
+    let mut basins: Vec<_> = basin_map.basins.iter().enumerate().collect();
+    basins.sort_by_key(|(_, basin)| std::cmp::Reverse(basin.size()));
+
+    for (id, basin) in basins {
+        let (i, j) = basin.low_point;
+        println!(
+            "Basin {}: size {}, low point ({}, {}) at height {}",
+            id,
+            basin.size(),
+            i,
+            j,
+            INPUT[i][j]
+        );
+    }
+    print!("{}", basin_map.render_labels());
+}
+
 pub fn part1() -> usize {
-    risk_level(&*INPUT)
+    risk_level(&INPUT)
 }
 
 pub fn part2() -> usize {
-    three_largest_basins(&*INPUT)
+    three_largest_basins(&INPUT)
 }
 
 #[cfg(test)]
@@ -144,11 +260,58 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(risk_level(&*INPUT), 15);
+        assert_eq!(risk_level(&INPUT), 15);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(three_largest_basins(&*INPUT), 1134);
+        assert_eq!(three_largest_basins(&INPUT), 1134);
+    }
+
+    #[test]
+    fn heightmap_rendering() {
+        let basin_map = basin_map(&INPUT);
+        assert_eq!(location_color(&INPUT, &basin_map, (0, 2)), [20, 20, 20]);
+        assert_eq!(location_color(&INPUT, &basin_map, (2, 2)), [220, 40, 40]);
+        // Surrounded by its own basin, so it isn't outlined.
+        assert_eq!(location_color(&INPUT, &basin_map, (2, 3)), [168, 200, 224]);
+        assert_eq!(location_color(&INPUT, &basin_map, (1, 2)), [239, 220, 126]);
+
+        let terminal = render_terminal(&INPUT, &basin_map);
+        assert_eq!(terminal.lines().count(), 5);
+        assert!(terminal.starts_with("\x1b[48;2;155;160;84m  "));
+
+        let ppm = render_ppm(&INPUT, &basin_map, 2);
+        let header = b"P6\n20 10\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 20 * 10 * 3);
+    }
+
+    #[test]
+    fn basin_labels() {
+        let basin_map = basin_map(&INPUT);
+        let summary: Vec<_> = basin_map
+            .basins
+            .iter()
+            .map(|basin| (basin.low_point, basin.size()))
+            .collect();
+        assert_eq!(
+            summary,
+            [((0, 1), 3), ((0, 9), 9), ((2, 2), 14), ((4, 6), 9)]
+        );
+        assert_eq!(basin_map.basins[0].members, [(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(basin_map.labels[0][2], None);
+        assert_eq!(basin_map.labels[4][9], Some(3));
+        assert!(basin_map
+            .render_labels()
+            .starts_with("00###11111\n0#222#1#11\n"));
+
+        // No location is strictly lower than all of its neighbors.
+        let plateau = [vec![5, 5, 9], vec![9, 5, 9], vec![9, 9, 9]];
+        assert_eq!(risk_level(&plateau), 0);
+        let basin_map = super::basin_map(&plateau);
+        assert_eq!(basin_map.basins.len(), 1);
+        assert_eq!(basin_map.basins[0].low_point, (0, 0));
+        assert_eq!(basin_map.basins[0].size(), 3);
     }
 }
@@ -1,4 +1,7 @@
 use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::io;
+use std::str::FromStr;
 
 use once_cell::sync::Lazy;
 
@@ -8,81 +11,281 @@ static INPUT: Lazy<Vec<String>> =
     Lazy::new(|| utils::get_input_as_vec_with(INPUT_PATH, |line| line.to_string()));
 const INPUT_PATH: &str = "input/day10";
 
-fn syntax_error_score(input: &[&str]) -> usize {
-    let score_map = HashMap::from([(b')', 3), (b']', 57), (b'}', 1197), (b'>', 25137)]);
-    let mut total_score = 0;
+// Columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LineStatus {
+    Valid,
+    Corrupted {
+        expected: char,
+        found: char,
+        column: usize,
+    },
+    Incomplete {
+        completion: String,
+    },
+    // A closing character with no chunk left to close.
+    UnexpectedCloser {
+        found: char,
+        column: usize,
+    },
+    InvalidChar {
+        found: char,
+        column: usize,
+    },
+}
 
-    for line in input {
-        if let (Some(ch), _) = illegal_character(line) {
-            total_score += score_map[&ch];
+impl Display for LineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineStatus::Valid => write!(f, "valid"),
+            LineStatus::Corrupted {
+                expected,
+                found,
+                column,
+            } => write!(
+                f,
+                "corrupted: expected {}, but found {} instead at column {}",
+                expected, found, column
+            ),
+            LineStatus::Incomplete { completion } => {
+                write!(f, "incomplete: complete by adding {}", completion)
+            }
+            LineStatus::UnexpectedCloser { found, column } => {
+                write!(f, "unexpected {} at column {}", found, column)
+            }
+            LineStatus::InvalidChar { found, column } => {
+                write!(f, "invalid character {:?} at column {}", found, column)
+            }
         }
     }
-
-    total_score
 }
 
-fn middle_score_of_incomplete_lines(input: &[&str]) -> usize {
-    let score_map = HashMap::from([(b')', 1), (b']', 2), (b'}', 3), (b'>', 4)]);
-    let mut scores = vec![];
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bracket {
+    open: char,
+    close: char,
+    corruption_score: usize,
+    completion_score: usize,
+}
 
-    input
-        .iter()
-        .map(|line| illegal_character(line))
-        .filter(|(ch, _)| ch.is_none())
-        .for_each(|(_, rem_stack)| {
-            let score = rem_stack
-                .into_iter()
-                .rev()
-                .map(get_closing_character)
-                .fold(0, |score, ch| score * 5 + score_map[&ch]);
+// Parses "OC:CORRUPTION:COMPLETION", e.g. "():3:1".
+impl FromStr for Bracket {
+    type Err = &'static str;
 
-            scores.push(score);
-        });
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let mut pair = parts.next().ok_or("Missing bracket pair")?.chars();
+        let (open, close) = match (pair.next(), pair.next(), pair.next()) {
+            (Some(open), Some(close), None) => (open, close),
+            _ => return Err("Bracket pair must be exactly two characters"),
+        };
+        let mut score = || -> Result<usize, &'static str> {
+            parts
+                .next()
+                .ok_or("Missing bracket score")?
+                .parse()
+                .map_err(|_| "Invalid bracket score")
+        };
+        let (corruption_score, completion_score) = (score()?, score()?);
+        if parts.next().is_some() {
+            return Err("Unexpected data after bracket scores");
+        }
 
-    let middle = scores.len() as f32 / 2.0;
-    let middle = middle.ceil() as usize - 1;
-    scores.sort_unstable();
+        Ok(Self {
+            open,
+            close,
+            corruption_score,
+            completion_score,
+        })
+    }
+}
 
-    scores[middle]
+#[derive(Debug, Clone)]
+struct BracketSet {
+    brackets: Vec<Bracket>,
+    // Index into `brackets` for each opening and closing character.
+    openers: HashMap<char, usize>,
+    closers: HashMap<char, usize>,
 }
 
-fn illegal_character(input: &str) -> (Option<u8>, Vec<u8>) {
-    let mut stack = vec![];
+impl BracketSet {
+    fn new(brackets: Vec<Bracket>) -> Result<Self, &'static str> {
+        if brackets.is_empty() {
+            return Err("At least one bracket pair is required");
+        }
+
+        let mut openers = HashMap::new();
+        let mut closers = HashMap::new();
+        for (i, bracket) in brackets.iter().enumerate() {
+            let (open, close) = (bracket.open, bracket.close);
+            if open == close
+                || [open, close]
+                    .iter()
+                    .any(|ch| openers.contains_key(ch) || closers.contains_key(ch))
+            {
+                return Err("Bracket characters must all be distinct");
+            }
+            openers.insert(open, i);
+            closers.insert(close, i);
+        }
+
+        Ok(Self {
+            brackets,
+            openers,
+            closers,
+        })
+    }
+
+    fn closer(&self, ch: char) -> Option<&Bracket> {
+        self.closers.get(&ch).map(|&i| &self.brackets[i])
+    }
+
+    fn check_line(&self, input: &str) -> LineStatus {
+        let mut stack = vec![];
 
-    for ch in input.bytes() {
-        match ch {
-            c @ (b'(' | b'[' | b'{' | b'<') => stack.push(c),
-            c @ (b')' | b']' | b'}' | b'>') => {
-                let top = stack.pop().unwrap();
-                if c != get_closing_character(top) {
-                    return (Some(c), stack);
+        for (column, ch) in input.chars().enumerate().map(|(i, ch)| (i + 1, ch)) {
+            if let Some(&i) = self.openers.get(&ch) {
+                stack.push(i);
+            } else if self.closers.contains_key(&ch) {
+                let expected = match stack.pop() {
+                    Some(top) => self.brackets[top].close,
+                    None => return LineStatus::UnexpectedCloser { found: ch, column },
+                };
+                if ch != expected {
+                    return LineStatus::Corrupted {
+                        expected,
+                        found: ch,
+                        column,
+                    };
                 }
+            } else {
+                return LineStatus::InvalidChar { found: ch, column };
+            }
+        }
+
+        if stack.is_empty() {
+            LineStatus::Valid
+        } else {
+            LineStatus::Incomplete {
+                completion: stack
+                    .into_iter()
+                    .rev()
+                    .map(|i| self.brackets[i].close)
+                    .collect(),
             }
-            _ => unreachable!(),
         }
     }
+}
 
-    (None, stack)
+impl Default for BracketSet {
+    fn default() -> Self {
+        Self::new(vec![
+            Bracket {
+                open: '(',
+                close: ')',
+                corruption_score: 3,
+                completion_score: 1,
+            },
+            Bracket {
+                open: '[',
+                close: ']',
+                corruption_score: 57,
+                completion_score: 2,
+            },
+            Bracket {
+                open: '{',
+                close: '}',
+                corruption_score: 1197,
+                completion_score: 3,
+            },
+            Bracket {
+                open: '<',
+                close: '>',
+                corruption_score: 25137,
+                completion_score: 4,
+            },
+        ])
+        .unwrap()
+    }
 }
 
-fn get_closing_character(ch: u8) -> u8 {
-    match ch {
-        b'(' => b')',
-        b'[' => b']',
-        b'{' => b'}',
-        b'<' => b'>',
-        _ => unreachable!(),
+fn syntax_error_score(brackets: &BracketSet, input: &[&str]) -> usize {
+    let mut total_score = 0;
+
+    for line in input {
+        if let LineStatus::Corrupted { found, .. } = brackets.check_line(line) {
+            total_score += brackets.closer(found).unwrap().corruption_score;
+        }
     }
+
+    total_score
+}
+
+// None if there are no incomplete lines.
+fn middle_score_of_incomplete_lines(brackets: &BracketSet, input: &[&str]) -> Option<usize> {
+    let mut scores = vec![];
+
+    input
+        .iter()
+        .filter_map(|line| match brackets.check_line(line) {
+            LineStatus::Incomplete { completion } => Some(completion),
+            _ => None,
+        })
+        .for_each(|completion| {
+            let score = completion.chars().fold(0, |score, ch| {
+                score * 5 + brackets.closer(ch).unwrap().completion_score
+            });
+
+            scores.push(score);
+        });
+
+    let middle = scores.len() as f32 / 2.0;
+    let middle = (middle.ceil() as usize).checked_sub(1)?;
+    scores.sort_unstable();
+
+    Some(scores[middle])
 }
 
 pub fn part1() -> usize {
     let input: Vec<&str> = INPUT.iter().map(|line| line.as_ref()).collect();
-    syntax_error_score(&input)
+    syntax_error_score(&BracketSet::default(), &input)
 }
 
 pub fn part2() -> usize {
     let input: Vec<&str> = INPUT.iter().map(|line| line.as_ref()).collect();
-    middle_score_of_incomplete_lines(&input)
+    middle_score_of_incomplete_lines(&BracketSet::default(), &input).unwrap()
+}
+
+// Without any brackets the puzzle input is checked with the puzzle's own set, otherwise lines
+// are read from stdin and checked with the given "OC:CORRUPTION:COMPLETION" pairs.
+pub fn print_report(brackets: &[&str]) -> Result<(), &'static str> {
+    let (brackets, input) = if brackets.is_empty() {
+        (BracketSet::default(), INPUT.clone())
+    } else {
+        let brackets = brackets
+            .iter()
+            .map(|bracket| bracket.parse())
+            .collect::<Result<_, _>>()?;
+        let input = io::stdin()
+            .lines()
+            .collect::<Result<_, _>>()
+            .map_err(|_| "Failed to read from stdin")?;
+        (BracketSet::new(brackets)?, input)
+    };
+    let input: Vec<&str> = input.iter().map(|line| line.as_ref()).collect();
+
+    for (i, line) in input.iter().enumerate() {
+        println!("Line {}: {}", i + 1, brackets.check_line(line));
+    }
+    println!(
+        "Syntax error score: {}",
+        syntax_error_score(&brackets, &input)
+    );
+    if let Some(score) = middle_score_of_incomplete_lines(&brackets, &input) {
+        println!("Middle completion score: {}", score);
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -104,11 +307,91 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(syntax_error_score(INPUT), 26397);
+        assert_eq!(syntax_error_score(&BracketSet::default(), INPUT), 26397);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(middle_score_of_incomplete_lines(INPUT), 288957);
+        assert_eq!(
+            middle_score_of_incomplete_lines(&BracketSet::default(), INPUT),
+            Some(288957)
+        );
+    }
+
+    #[test]
+    fn line_status() {
+        let brackets = BracketSet::default();
+
+        assert_eq!(
+            brackets.check_line(INPUT[0]),
+            LineStatus::Incomplete {
+                completion: String::from("}}]])})]")
+            }
+        );
+        assert_eq!(
+            brackets.check_line(INPUT[2]),
+            LineStatus::Corrupted {
+                expected: ']',
+                found: '}',
+                column: 13
+            }
+        );
+        assert_eq!(brackets.check_line("{([])}<>"), LineStatus::Valid);
+        assert_eq!(
+            brackets.check_line("()]"),
+            LineStatus::UnexpectedCloser {
+                found: ']',
+                column: 3
+            }
+        );
+        assert_eq!(
+            brackets.check_line("(a)"),
+            LineStatus::InvalidChar {
+                found: 'a',
+                column: 2
+            }
+        );
+        assert_eq!(
+            brackets.check_line(INPUT[2]).to_string(),
+            "corrupted: expected ], but found } instead at column 13"
+        );
+    }
+
+    #[test]
+    fn custom_brackets() {
+        let brackets: Vec<Bracket> = ["ab:10:1", "xy:20:2"]
+            .iter()
+            .map(|bracket| bracket.parse().unwrap())
+            .collect();
+        let brackets = BracketSet::new(brackets).unwrap();
+        let input = &["axyb", "axb", "ax", "ab("];
+
+        assert_eq!(brackets.check_line(input[0]), LineStatus::Valid);
+        assert_eq!(
+            brackets.check_line(input[2]),
+            LineStatus::Incomplete {
+                completion: String::from("yb")
+            }
+        );
+        assert_eq!(syntax_error_score(&brackets, input), 10);
+        assert_eq!(middle_score_of_incomplete_lines(&brackets, input), Some(11));
+        assert_eq!(
+            middle_score_of_incomplete_lines(&brackets, &input[..2]),
+            None
+        );
+
+        assert_eq!(
+            "(:3:1".parse::<Bracket>().unwrap_err(),
+            "Bracket pair must be exactly two characters"
+        );
+        assert_eq!(
+            "():3".parse::<Bracket>().unwrap_err(),
+            "Missing bracket score"
+        );
+        assert_eq!(
+            BracketSet::new(vec!["():3:1".parse().unwrap(), "(]:3:1".parse().unwrap()])
+                .unwrap_err(),
+            "Bracket characters must all be distinct"
+        );
     }
 }
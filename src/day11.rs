@@ -1,4 +1,7 @@
-use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
 
 use once_cell::sync::Lazy;
 
@@ -11,94 +14,153 @@ static INPUT: Lazy<Vec<Vec<u8>>> = Lazy::new(|| {
 });
 const INPUT_PATH: &str = "input/day11";
 
-#[derive(Clone, Copy)]
-enum Status {
-    Unvisited,
-    Enqueued,
+// Energy level of an octopus that is about to flash in the current step. It stays there until
+// the end of the step, so every octopus is pushed to the stack at most once.
+const FLASHING: u8 = 10;
+
+struct OctopusGrid {
+    octopuses: Vec<Vec<u8>>,
+    // Octopuses that reached `FLASHING` but haven't raised their neighbors' energy yet. Kept
+    // around so steps don't need to allocate it again.
+    to_flash: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StepReport {
+    flashes: usize,
+    // In the order the octopuses flashed.
+    flashed: Vec<(usize, usize)>,
+    all_flashed: bool,
 }
 
-fn flashes_after_n_steps(mut octopuses: Vec<Vec<u8>>, n: usize, sync: bool) -> (usize, usize) {
-    let mut count = 0;
+impl OctopusGrid {
+    fn new(octopuses: Vec<Vec<u8>>) -> Self {
+        Self {
+            octopuses,
+            to_flash: Vec::new(),
+        }
+    }
+
+    fn step(&mut self) -> StepReport {
+        let (height, width) = (self.octopuses.len(), self.octopuses[0].len());
+        let mut flashed = Vec::new();
 
-    for curr in 0..n {
-        for row in &mut octopuses {
-            for octopus in row {
+        for (i, row) in self.octopuses.iter_mut().enumerate() {
+            for (j, octopus) in row.iter_mut().enumerate() {
                 *octopus += 1;
+                if *octopus == FLASHING {
+                    self.to_flash.push((i, j));
+                }
             }
         }
 
-        for i in 0..octopuses.len() {
-            for j in 0..octopuses[0].len() {
-                if octopuses[i][j] > 9 {
-                    let mut to_visit = VecDeque::new();
-                    let mut status =
-                        vec![vec![Status::Unvisited; octopuses[0].len()]; octopuses.len()];
-                    to_visit.push_back((i, j));
-                    status[i][j] = Status::Enqueued;
-
-                    count += flash(&mut octopuses, &mut to_visit, &mut status);
+        while let Some((i, j)) = self.to_flash.pop() {
+            flashed.push((i, j));
 
-                    if sync && octopuses.iter().flatten().all(|&a| a == 0) {
-                        return (count, curr + 1);
+            for x in i.saturating_sub(1)..=(i + 1).min(height - 1) {
+                for y in j.saturating_sub(1)..=(j + 1).min(width - 1) {
+                    let neighbor = &mut self.octopuses[x][y];
+                    if *neighbor < FLASHING {
+                        *neighbor += 1;
+                        if *neighbor == FLASHING {
+                            self.to_flash.push((x, y));
+                        }
                     }
                 }
             }
         }
+
+        for &(i, j) in &flashed {
+            self.octopuses[i][j] = 0;
+        }
+
+        StepReport {
+            flashes: flashed.len(),
+            all_flashed: flashed.len() == height * width,
+            flashed,
+        }
     }
 
-    (count, 0)
-}
+    // Never ends; the octopuses keep flashing forever.
+    fn steps(&mut self) -> impl Iterator<Item = StepReport> + '_ {
+        std::iter::repeat_with(move || self.step())
+    }
+
+    // Same as the `Display` output, except that octopuses that just flashed are highlighted
+    // and the rest are dimmed, using ANSI escape codes.
+    fn render_frame(&self, flashed: &[(usize, usize)]) -> String {
+        let mut highlighted = vec![vec![false; self.octopuses[0].len()]; self.octopuses.len()];
+        for &(i, j) in flashed {
+            highlighted[i][j] = true;
+        }
 
-fn flash(
-    octopuses: &mut [Vec<u8>],
-    to_visit: &mut VecDeque<(usize, usize)>,
-    status: &mut [Vec<Status>],
-) -> usize {
-    let mut count = 0;
-
-    while let Some((i, j)) = to_visit.pop_front() {
-        octopuses[i][j] = 0;
-        count += 1;
-
-        for (i, j) in get_neighbors(octopuses, (i, j)) {
-            let neighbor = &mut octopuses[i][j];
-            // "< 10" comparison is only to prevent overflow
-            if *neighbor > 0 && *neighbor < 10 {
-                *neighbor += 1;
+        let mut frame = String::new();
+        for (row, highlighted) in self.octopuses.iter().zip(highlighted) {
+            for (octopus, highlighted) in row.iter().zip(highlighted) {
+                let style = if highlighted { "1;93" } else { "2" };
+                frame.push_str(&format!("\x1b[{}m{}\x1b[0m", style, octopus));
             }
-            if *neighbor > 9 {
-                if let Status::Unvisited = status[i][j] {
-                    to_visit.push_back((i, j));
-                    status[i][j] = Status::Enqueued;
-                }
+            frame.push('\n');
+        }
+
+        frame
+    }
+}
+
+impl Display for OctopusGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.octopuses {
+            for &octopus in row {
+                write!(f, "{}", octopus)?;
             }
+            writeln!(f)?;
         }
+
+        Ok(())
     }
+}
 
-    count
+fn flashes_after_n_steps(octopuses: Vec<Vec<u8>>, n: usize) -> usize {
+    OctopusGrid::new(octopuses)
+        .steps()
+        .take(n)
+        .map(|report| report.flashes)
+        .sum()
 }
 
-fn get_neighbors(octopuses: &[Vec<u8>], origin: (usize, usize)) -> Vec<(usize, usize)> {
-    let (i, j) = origin;
-    let mut neighbors = Vec::with_capacity(8);
+fn first_synchronized_step(octopuses: Vec<Vec<u8>>) -> usize {
+    OctopusGrid::new(octopuses)
+        .steps()
+        .position(|report| report.all_flashed)
+        .unwrap()
+        + 1
+}
 
-    for x in i.checked_sub(1).unwrap_or_default()..=i + 1 {
-        for y in j.checked_sub(1).unwrap_or_default()..=j + 1 {
-            if (x != i || y != j) && x < octopuses.len() && y < octopuses[0].len() {
-                neighbors.push((x, y));
-            }
+// Redraws the grid in place after every step, until all octopuses flash at once.
+pub fn animate(delay: Duration) {
+    let mut grid = OctopusGrid::new(INPUT.clone());
+    // Clear the screen once; later frames only move the cursor back to the top.
+    print!("\x1b[2J");
+
+    for step in 1.. {
+        let report = grid.step();
+        print!("\x1b[H{}", grid.render_frame(&report.flashed));
+        println!("Step {}: {} flashes", step, report.flashes);
+        io::stdout().flush().unwrap();
+
+        if report.all_flashed {
+            break;
         }
+        thread::sleep(delay);
     }
-
-    neighbors
 }
 
 pub fn part1() -> usize {
-    flashes_after_n_steps(INPUT.clone(), 100, false).0
+    flashes_after_n_steps(INPUT.clone(), 100)
 }
 
 pub fn part2() -> usize {
-    flashes_after_n_steps(INPUT.clone(), usize::MAX, true).1
+    first_synchronized_step(INPUT.clone())
 }
 
 #[cfg(test)]
@@ -122,14 +184,79 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(flashes_after_n_steps(INPUT.clone(), 100, false).0, 1656);
+        assert_eq!(flashes_after_n_steps(INPUT.clone(), 10), 204);
+        assert_eq!(flashes_after_n_steps(INPUT.clone(), 100), 1656);
     }
 
     #[test]
     fn part2_example() {
+        assert_eq!(first_synchronized_step(INPUT.clone()), 195);
+    }
+
+    #[test]
+    fn large_grid() {
+        assert_eq!(first_synchronized_step(vec![vec![0; 1000]; 1000]), 10);
+
+        // Pseudo-random energy levels, so flashes ripple through the whole grid.
+        let mut seed = 42u32;
+        let octopuses: Vec<Vec<u8>> = (0..1000)
+            .map(|_| {
+                (0..1000)
+                    .map(|_| {
+                        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                        (seed >> 16) as u8 % 10
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut grid = OctopusGrid::new(octopuses);
+        for report in grid.steps().take(3) {
+            assert!(report.flashes > 0);
+        }
+        assert!(grid.octopuses.iter().flatten().all(|&octopus| octopus < 10));
+    }
+
+    #[test]
+    fn step_reports() {
+        let mut grid = OctopusGrid::new(vec![
+            vec![1, 1, 1, 1, 1],
+            vec![1, 9, 9, 9, 1],
+            vec![1, 9, 1, 9, 1],
+            vec![1, 9, 9, 9, 1],
+            vec![1, 1, 1, 1, 1],
+        ]);
+
+        let mut report = grid.step();
+        report.flashed.sort_unstable();
+        assert_eq!(report.flashes, 9);
         assert_eq!(
-            flashes_after_n_steps(INPUT.clone(), usize::MAX, true).1,
-            195
+            report.flashed,
+            [
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (2, 1),
+                (2, 2),
+                (2, 3),
+                (3, 1),
+                (3, 2),
+                (3, 3)
+            ]
         );
+        assert!(!report.all_flashed);
+        assert_eq!(grid.to_string(), "34543\n40004\n50005\n40004\n34543\n");
+
+        let report = grid.step();
+        assert_eq!(report.flashes, 0);
+        assert_eq!(grid.to_string(), "45654\n51115\n61116\n51115\n45654\n");
+
+        let frame = grid.render_frame(&[(0, 1)]);
+        assert!(frame.starts_with("\x1b[2m4\x1b[0m\x1b[1;93m5\x1b[0m\x1b[2m6\x1b[0m"));
+        assert_eq!(frame.lines().count(), 5);
+
+        let mut grid = OctopusGrid::new(INPUT.clone());
+        let report = grid.steps().nth(194).unwrap();
+        assert!(report.all_flashed);
+        assert_eq!(report.flashes, 100);
     }
 }
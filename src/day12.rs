@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt::Write;
 
 use once_cell::sync::Lazy;
 
@@ -7,160 +8,215 @@ use crate::utils;
 static INPUT: Lazy<Vec<String>> = Lazy::new(|| utils::get_input_as_vec(INPUT_PATH));
 const INPUT_PATH: &str = "input/day12";
 
-#[derive(Debug)]
-struct Graph<'a> {
-    nodes: HashMap<&'a str, usize>,
-    adjacency_matrix: Vec<Vec<usize>>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaveKind {
+    Start,
+    End,
+    Big,
+    Small,
 }
 
-impl<'a> Graph<'a> {
-    fn build(paths: &[&'a str]) -> Self {
-        let mut nodes = HashMap::new();
-        let mut id = 0;
-        let mut record = |node| {
-            if node != "start" && node != "end" {
-                nodes.entry(node).or_insert_with(|| {
-                    id += 1;
-                    id
-                });
-            }
+impl CaveKind {
+    fn of(name: &str) -> Result<Self, &'static str> {
+        let kind = match name {
+            "start" => CaveKind::Start,
+            "end" => CaveKind::End,
+            "" => return Err("Empty cave name"),
+            _ if name.bytes().all(|b| b.is_ascii_uppercase()) => CaveKind::Big,
+            _ if name.bytes().all(|b| b.is_ascii_lowercase()) => CaveKind::Small,
+            _ => return Err("Cave names must be either all uppercase or all lowercase"),
         };
 
-        for path in paths {
-            let (lhs, rhs) = path.split_once('-').unwrap();
-            record(lhs);
-            record(rhs);
-        }
+        Ok(kind)
+    }
+}
 
-        nodes.insert("start", 0);
-        id += 1;
-        nodes.insert("end", id);
-        id += 1;
+#[derive(Debug)]
+struct Graph<'a> {
+    names: Vec<&'a str>,
+    kinds: Vec<CaveKind>,
+    adjacency_lists: Vec<Vec<usize>>,
+    // Edges in input order, used for exporting the graph.
+    edges: Vec<(usize, usize)>,
+    start: usize,
+    end: usize,
+    visited: Vec<bool>,
+    // Number of paths that went through each edge during the last enumeration.
+    traversals: HashMap<(usize, usize), usize>,
+}
 
-        let mut adjacency_matrix = vec![vec![0; id]; id];
+impl<'a> Graph<'a> {
+    fn build(paths: &[&'a str]) -> Result<Self, &'static str> {
+        let mut ids = HashMap::new();
+        let mut names = Vec::new();
+        let mut kinds = Vec::new();
+        let mut adjacency_lists: Vec<Vec<usize>> = Vec::new();
+        let mut edges = Vec::new();
 
-        // Set the MSB to create an edge connecting the `lhs` and `rhs`.
         for path in paths {
-            let (lhs, rhs) = path.split_once('-').unwrap();
-            let (i, j) = (nodes[lhs], nodes[rhs]);
-            adjacency_matrix[i][j] = 0x01 << (usize::BITS - 1);
-            adjacency_matrix[j][i] = 0x01 << (usize::BITS - 1);
-        }
-
-        // Set bit 1 on all rows of nodes that represent big caves.
-        for (cave, &node_id) in &nodes {
-            if cave.chars().next().unwrap().is_ascii_uppercase() {
-                for j in 0..adjacency_matrix.len() {
-                    adjacency_matrix[node_id][j] |= 0x02;
+            let (lhs, rhs) = path.split_once('-').ok_or("Missing '-' between caves")?;
+            let mut record = |cave| -> Result<usize, &'static str> {
+                if let Some(&id) = ids.get(cave) {
+                    return Ok(id);
                 }
+                kinds.push(CaveKind::of(cave)?);
+                names.push(cave);
+                adjacency_lists.push(Vec::new());
+                ids.insert(cave, names.len() - 1);
+                Ok(names.len() - 1)
+            };
+            let (u, v) = (record(lhs)?, record(rhs)?);
+
+            // Two connected big caves can be bounced between forever.
+            if kinds[u] == CaveKind::Big && kinds[v] == CaveKind::Big {
+                return Err("Edge between two big caves yields infinitely many paths");
             }
+            if adjacency_lists[u].contains(&v) {
+                continue;
+            }
+            adjacency_lists[u].push(v);
+            if u != v {
+                adjacency_lists[v].push(u);
+            }
+            edges.push((u, v));
         }
 
-        Self {
-            nodes,
-            adjacency_matrix,
-        }
+        let start = *ids.get("start").ok_or("Missing 'start' cave")?;
+        let end = *ids.get("end").ok_or("Missing 'end' cave")?;
+        let len = names.len();
+
+        Ok(Self {
+            names,
+            kinds,
+            adjacency_lists,
+            edges,
+            start,
+            end,
+            visited: vec![false; len],
+            traversals: HashMap::new(),
+        })
     }
 
     fn all_paths(&mut self) -> usize {
         let mut count = 0;
-        self.visit(self.nodes["start"], &mut count);
+        self.traversals.clear();
+        self.visit(self.start, &mut count);
         count
     }
 
     fn visit(&mut self, node_id: usize, count: &mut usize) {
-        if node_id == self.nodes["end"] {
+        if node_id == self.end {
             *count += 1;
             return;
         }
 
-        let nodes = &self.adjacency_matrix[node_id];
-        // Iteration starts at 1 so we don't visit the "start" node.
-        for v in 1..nodes.len() {
-            if self.has_edge(node_id, v) && (self.is_big(v) || !self.visited(v)) {
-                self.mark_visited(v);
+        for k in 0..self.adjacency_lists[node_id].len() {
+            let v = self.adjacency_lists[node_id][k];
+            let allowed = match self.kinds[v] {
+                CaveKind::Start => false,
+                CaveKind::Big => true,
+                CaveKind::End | CaveKind::Small => !self.visited[v],
+            };
+            if allowed {
+                let before = *count;
+                self.visited[v] = true;
                 self.visit(v, count);
-                self.mark_unvisited(v);
+                self.visited[v] = false;
+                self.record_traversals(node_id, v, *count - before);
             }
         }
     }
 
     fn all_paths2(&mut self) -> usize {
         let mut count = 0;
-        self.visit2(self.nodes["start"], &mut count, &mut None);
+        self.traversals.clear();
+        self.visit2(self.start, &mut count, &mut None);
         count
     }
 
     fn visit2(&mut self, node_id: usize, count: &mut usize, small_cave_twice: &mut Option<usize>) {
-        if node_id == self.nodes["end"] {
+        if node_id == self.end {
             *count += 1;
             return;
         }
 
-        let nodes = &self.adjacency_matrix[node_id];
-        // Iteration starts at 1 so we don't visit the "start" node.
-        for v in 1..nodes.len() {
-            if self.has_edge(node_id, v) {
-                if small_cave_twice.is_none() && self.is_small(v) && self.visited(v) {
-                    *small_cave_twice = Some(v);
-                } else if self.is_small(v) && self.visited(v) {
+        for k in 0..self.adjacency_lists[node_id].len() {
+            let v = self.adjacency_lists[node_id][k];
+            let revisit = match self.kinds[v] {
+                CaveKind::Start => continue,
+                CaveKind::Big => false,
+                CaveKind::End | CaveKind::Small => self.visited[v],
+            };
+            if revisit {
+                if small_cave_twice.is_some() || self.kinds[v] == CaveKind::End {
                     continue;
                 }
-                self.mark_visited(v);
-                self.visit2(v, count, small_cave_twice);
-                if let Some(visited) = small_cave_twice {
-                    if *visited == v {
-                        *small_cave_twice = None;
-                        continue;
-                    }
-                }
-                self.mark_unvisited(v);
+                *small_cave_twice = Some(v);
+            }
+
+            let before = *count;
+            self.visited[v] = true;
+            self.visit2(v, count, small_cave_twice);
+            self.record_traversals(node_id, v, *count - before);
+            if revisit {
+                *small_cave_twice = None;
+            } else {
+                self.visited[v] = false;
             }
         }
     }
 
-    // If the MSB is set, then there is an edge connecting the nodes.
-    fn has_edge(&self, u: usize, v: usize) -> bool {
-        self.adjacency_matrix[u][v] & (0x01 << (usize::BITS - 1)) != 0
+    // Edges are undirected, so traversals in both directions share the same entry.
+    fn record_traversals(&mut self, u: usize, v: usize, paths: usize) {
+        *self.traversals.entry((u.min(v), u.max(v))).or_insert(0) += paths;
     }
 
-    // If the LSB is set, then this node has already been visited.
-    fn visited(&self, node_id: usize) -> bool {
-        self.adjacency_matrix[0][node_id] & 0x01 != 0
-    }
+    // Big caves are drawn as boxes, small caves as circles. If `annotate` is set, each edge is
+    // labelled with the number of paths that went through it in the last enumeration.
+    fn to_dot(&self, annotate: bool) -> String {
+        let mut dot = String::from("graph caves {\n");
 
-    // Set the LSB of all rows in this node's column to mark it as visited.
-    fn mark_visited(&mut self, node_id: usize) {
-        for i in 0..self.adjacency_matrix.len() {
-            self.adjacency_matrix[i][node_id] |= 0x01;
+        for (name, kind) in self.names.iter().zip(&self.kinds) {
+            let attributes = match kind {
+                CaveKind::Start => "shape=doublecircle, style=filled, fillcolor=palegreen",
+                CaveKind::End => "shape=doublecircle, style=filled, fillcolor=lightcoral",
+                CaveKind::Big => "shape=box",
+                CaveKind::Small => "shape=circle",
+            };
+            writeln!(dot, "    \"{}\" [{}];", name, attributes).unwrap();
         }
-    }
 
-    // Unset the LSB of all rows in this node's column to mark it as unvisited.
-    fn mark_unvisited(&mut self, node_id: usize) {
-        for i in 0..self.adjacency_matrix.len() {
-            self.adjacency_matrix[i][node_id] &= !0x01;
+        for &(u, v) in &self.edges {
+            write!(dot, "    \"{}\" -- \"{}\"", self.names[u], self.names[v]).unwrap();
+            if annotate {
+                let paths = self.traversals.get(&(u.min(v), u.max(v))).unwrap_or(&0);
+                write!(dot, " [label={}]", paths).unwrap();
+            }
+            dot.push_str(";\n");
         }
-    }
-
-    // If bit 1 is set, then this node represents a big cave.
-    fn is_big(&self, node_id: usize) -> bool {
-        self.adjacency_matrix[node_id][0] & 0x02 != 0
-    }
 
-    fn is_small(&self, node_id: usize) -> bool {
-        !self.is_big(node_id)
+        dot.push_str("}\n");
+        dot
     }
 }
 
 pub fn part1() -> usize {
     let paths: Vec<&str> = INPUT.iter().map(|path| path.as_ref()).collect();
-    Graph::build(&paths).all_paths()
+    Graph::build(&paths).unwrap().all_paths()
 }
 
 pub fn part2() -> usize {
     let paths: Vec<&str> = INPUT.iter().map(|path| path.as_ref()).collect();
-    Graph::build(&paths).all_paths2()
+    Graph::build(&paths).unwrap().all_paths2()
+}
+
+pub fn dot(annotate: bool) -> String {
+    let paths: Vec<&str> = INPUT.iter().map(|path| path.as_ref()).collect();
+    let mut graph = Graph::build(&paths).unwrap();
+    if annotate {
+        graph.all_paths2();
+    }
+    graph.to_dot(annotate)
 }
 
 #[cfg(test)]
@@ -179,15 +235,55 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(Graph::build(INPUT1).all_paths(), 10);
-        assert_eq!(Graph::build(INPUT2).all_paths(), 19);
-        assert_eq!(Graph::build(INPUT3).all_paths(), 226);
+        assert_eq!(Graph::build(INPUT1).unwrap().all_paths(), 10);
+        assert_eq!(Graph::build(INPUT2).unwrap().all_paths(), 19);
+        assert_eq!(Graph::build(INPUT3).unwrap().all_paths(), 226);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(Graph::build(INPUT1).all_paths2(), 36);
-        assert_eq!(Graph::build(INPUT2).all_paths2(), 103);
-        assert_eq!(Graph::build(INPUT3).all_paths2(), 3509);
+        assert_eq!(Graph::build(INPUT1).unwrap().all_paths2(), 36);
+        assert_eq!(Graph::build(INPUT2).unwrap().all_paths2(), 103);
+        assert_eq!(Graph::build(INPUT3).unwrap().all_paths2(), 3509);
+    }
+
+    #[test]
+    fn dot_export() {
+        let mut graph = Graph::build(INPUT1).unwrap();
+        graph.all_paths();
+        let dot = graph.to_dot(true);
+
+        assert!(dot.starts_with("graph caves {\n"));
+        assert!(dot.contains("\"start\" [shape=doublecircle, style=filled, fillcolor=palegreen];"));
+        assert!(dot.contains("\"A\" [shape=box];"));
+        assert!(dot.contains("\"b\" [shape=circle];"));
+        // All 10 paths leave "start" through either "A" or "b".
+        assert!(dot.contains("\"start\" -- \"A\" [label=7];"));
+        assert!(dot.contains("\"start\" -- \"b\" [label=3];"));
+        assert_eq!(dot.matches(" -- ").count(), INPUT1.len());
+    }
+
+    #[test]
+    fn invalid_graphs() {
+        assert_eq!(
+            Graph::build(&["start-A", "A-B", "B-end"]).unwrap_err(),
+            "Edge between two big caves yields infinitely many paths"
+        );
+        assert_eq!(
+            Graph::build(&["A-b", "b-end"]).unwrap_err(),
+            "Missing 'start' cave"
+        );
+        assert_eq!(
+            Graph::build(&["start-A", "A-b"]).unwrap_err(),
+            "Missing 'end' cave"
+        );
+        assert_eq!(
+            Graph::build(&["start-A", "A-bC", "bC-end"]).unwrap_err(),
+            "Cave names must be either all uppercase or all lowercase"
+        );
+        assert_eq!(
+            Graph::build(&["start", "A-end"]).unwrap_err(),
+            "Missing '-' between caves"
+        );
     }
 }
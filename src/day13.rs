@@ -1,5 +1,8 @@
 use std::collections::HashSet;
+use std::fmt::{self, Display, Write};
 use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 
 use once_cell::sync::Lazy;
 
@@ -16,79 +19,343 @@ static COORDINATES: Lazy<Vec<(usize, usize)>> = Lazy::new(|| {
         })
         .collect()
 });
-static FOLD_INSTRUCTIONS: Lazy<Vec<(u8, usize)>> = Lazy::new(|| {
+static FOLD_INSTRUCTIONS: Lazy<Vec<Fold>> = Lazy::new(|| {
     INPUT
         .split("\n\n")
         .nth(1)
         .unwrap()
         .lines()
-        .map(|line| {
-            let coordinate = line.trim_start_matches("fold along ");
-            (
-                coordinate.bytes().next().unwrap(),
-                coordinate[2..].parse().unwrap(),
-            )
-        })
+        .map(|line| line.parse().unwrap())
         .collect()
 });
 const INPUT_PATH: &str = "input/day13";
 
+type Dots = HashSet<(usize, usize)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl Axis {
+    fn coordinate(self, (x, y): (usize, usize)) -> usize {
+        match self {
+            Axis::X => x,
+            Axis::Y => y,
+        }
+    }
+
+    fn with_coordinate(self, (x, y): (usize, usize), value: usize) -> (usize, usize) {
+        match self {
+            Axis::X => (value, y),
+            Axis::Y => (x, value),
+        }
+    }
+}
+
+impl FromStr for Axis {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let axis = match s {
+            "x" => Axis::X,
+            "y" => Axis::Y,
+            _ => return Err("Not an axis"),
+        };
+
+        Ok(axis)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fold {
+    axis: Axis,
+    at: usize,
+}
+
+impl Fold {
+    // Dots beyond the fold line are mirrored onto the other side. `shift` moves every dot away
+    // from the origin, making room for a folded part longer than the part it's folded onto.
+    fn apply(self, dot: (usize, usize), shift: usize) -> (usize, usize) {
+        let coordinate = self.axis.coordinate(dot);
+        let folded = if coordinate > self.at {
+            2 * self.at + shift - coordinate
+        } else {
+            coordinate + shift
+        };
+
+        self.axis.with_coordinate(dot, folded)
+    }
+}
+
+impl Display for Fold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let axis = match self.axis {
+            Axis::X => 'x',
+            Axis::Y => 'y',
+        };
+        write!(f, "fold along {}={}", axis, self.at)
+    }
+}
+
+impl FromStr for Fold {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (axis, at) = s
+            .strip_prefix("fold along ")
+            .ok_or("Missing 'fold along'")?
+            .split_once('=')
+            .ok_or("Missing '='")?;
+
+        Ok(Fold {
+            axis: axis.parse()?,
+            at: at.parse().map_err(|_| "Not a usize")?,
+        })
+    }
+}
+
 fn visible_dots_after_folding(
     coordinates: &[(usize, usize)],
-    fold_instructions: &[(u8, usize)],
-) -> (HashSet<(usize, usize)>, usize) {
+    fold_instructions: &[Fold],
+) -> Result<(Dots, usize), &'static str> {
     let mut coordinates: HashSet<_> = coordinates.iter().copied().collect();
 
-    for &(direction, split) in fold_instructions {
-        let folded: Vec<_> = coordinates
-            .iter()
-            .filter(|&&entry| beyond_split(direction, split, entry))
-            .map(|&entry| fold(direction, split, entry))
-            .collect();
-
-        coordinates.retain(|&entry| !beyond_split(direction, split, entry));
-        coordinates.extend(folded);
+    for &instruction in fold_instructions {
+        apply_fold(&mut coordinates, instruction)?;
     }
 
     let len = coordinates.len();
 
-    (coordinates, len)
+    Ok((coordinates, len))
 }
 
-fn beyond_split(direction: u8, split: usize, (x, y): (usize, usize)) -> bool {
-    if direction == b'x' {
-        x > split
-    } else {
-        y > split
-    }
+// Yields the dots left on the paper after each fold.
+fn folding_steps<'a>(
+    coordinates: &[(usize, usize)],
+    fold_instructions: &'a [Fold],
+) -> impl Iterator<Item = Result<Dots, &'static str>> + 'a {
+    let mut coordinates: HashSet<_> = coordinates.iter().copied().collect();
+
+    fold_instructions.iter().map(move |&instruction| {
+        apply_fold(&mut coordinates, instruction)?;
+        Ok(coordinates.clone())
+    })
 }
 
-fn fold(direction: u8, split: usize, (x, y): (usize, usize)) -> (usize, usize) {
-    if direction == b'x' {
-        (2 * split - x, y)
-    } else {
-        (x, 2 * split - y)
+fn apply_fold(coordinates: &mut Dots, fold: Fold) -> Result<(), &'static str> {
+    if coordinates
+        .iter()
+        .any(|&dot| fold.axis.coordinate(dot) == fold.at)
+    {
+        return Err("Fold line goes through a dot");
     }
+
+    let farthest = coordinates
+        .iter()
+        .map(|&dot| fold.axis.coordinate(dot))
+        .max()
+        .unwrap_or(0);
+    let shift = farthest.saturating_sub(2 * fold.at);
+    *coordinates = coordinates
+        .iter()
+        .map(|&dot| fold.apply(dot, shift))
+        .collect();
+
+    Ok(())
 }
 
 pub fn part1() -> usize {
-    visible_dots_after_folding(&*COORDINATES, &FOLD_INSTRUCTIONS[..1]).1
+    visible_dots_after_folding(&COORDINATES, &FOLD_INSTRUCTIONS[..1])
+        .unwrap()
+        .1
+}
+
+// Glyphs of the 4x6 font used by the puzzles, separated from each other by one empty column.
+// 'Y' is left out because it's five columns wide.
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+const GLYPHS: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+fn render(coordinates: &HashSet<(usize, usize)>) -> Vec<String> {
+    render_with_fold(coordinates, None)
 }
 
-pub fn part2() {
-    let (coords, _) = visible_dots_after_folding(&*COORDINATES, &*FOLD_INSTRUCTIONS);
-    let width = coords.iter().map(|&(x, _)| x).max().unwrap();
-    let height = coords.iter().map(|&(_, y)| y).max().unwrap();
-    let mut map = vec![vec![b'.'; width + 1]; height + 1];
-    for (x, y) in coords {
+// The paper is drawn large enough to contain the fold line, which is marked with `|` or `-`.
+fn render_with_fold(coordinates: &HashSet<(usize, usize)>, fold: Option<Fold>) -> Vec<String> {
+    let (width, height) = paper_size(coordinates, fold);
+    let mut map = vec![vec![b'.'; width]; height];
+    match fold {
+        Some(Fold { axis: Axis::X, at }) => map.iter_mut().for_each(|row| row[at] = b'|'),
+        Some(Fold { axis: Axis::Y, at }) => map[at].fill(b'-'),
+        None => {}
+    }
+    for &(x, y) in coordinates {
         map[y][x] = b'#';
     }
-    for row in map {
-        for entry in row {
-            print!("{}", char::from(entry));
+
+    map.into_iter()
+        .map(|row| String::from_utf8(row).unwrap())
+        .collect()
+}
+
+fn render_svg(coordinates: &HashSet<(usize, usize)>, fold: Option<Fold>) -> String {
+    const SCALE: usize = 10;
+
+    let (width, height) = paper_size(coordinates, fold);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        width * SCALE,
+        height * SCALE
+    );
+    svg.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    let mut dots: Vec<_> = coordinates.iter().collect();
+    dots.sort_unstable_by_key(|&&(x, y)| (y, x));
+    for (x, y) in dots {
+        writeln!(
+            svg,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>",
+            x * SCALE,
+            y * SCALE,
+            SCALE,
+            SCALE
+        )
+        .unwrap();
+    }
+
+    if let Some(Fold { axis, at }) = fold {
+        let middle = at * SCALE + SCALE / 2;
+        let (x1, y1, x2, y2) = match axis {
+            Axis::X => (middle, 0, middle, height * SCALE),
+            Axis::Y => (0, middle, width * SCALE, middle),
+        };
+        writeln!(
+            svg,
+            "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"red\" stroke-dasharray=\"4\"/>",
+            x1, y1, x2, y2
+        )
+        .unwrap();
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn paper_size(coordinates: &HashSet<(usize, usize)>, fold: Option<Fold>) -> (usize, usize) {
+    let mut width = coordinates.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let mut height = coordinates.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+    match fold {
+        Some(Fold { axis: Axis::X, at }) => width = width.max(2 * at + 1),
+        Some(Fold { axis: Axis::Y, at }) => height = height.max(2 * at + 1),
+        None => {}
+    }
+
+    (width, height)
+}
+
+// Pairs the paper before each fold with that fold, followed by the final paper.
+fn folding_frames(
+    coordinates: &[(usize, usize)],
+    fold_instructions: &[Fold],
+) -> Result<Vec<(Dots, Option<Fold>)>, &'static str> {
+    let papers = std::iter::once(Ok(coordinates.iter().copied().collect()))
+        .chain(folding_steps(coordinates, fold_instructions));
+    let folds = fold_instructions.iter().copied().map(Some).chain([None]);
+
+    papers
+        .zip(folds)
+        .map(|(paper, fold)| Ok((paper?, fold)))
+        .collect()
+}
+
+// Returns `None` if the dots don't spell a sequence of known glyphs.
+fn recognize(rows: &[String]) -> Option<String> {
+    if rows.len() != GLYPH_HEIGHT {
+        return None;
+    }
+
+    let width = rows[0].len();
+    // The last glyph is cut short when its rightmost columns have no dots.
+    let count = width.div_ceil(GLYPH_WIDTH + 1);
+    if count == 0 {
+        return None;
+    }
+
+    (0..count)
+        .map(|i| {
+            let from = i * (GLYPH_WIDTH + 1);
+            let to = (from + GLYPH_WIDTH).min(width);
+            let glyph = rows.iter().map(|row| {
+                let mut column = row[from..to].to_owned();
+                column.extend(std::iter::repeat_n('.', GLYPH_WIDTH - (to - from)));
+                column
+            });
+
+            GLYPHS
+                .iter()
+                .find(|(_, pattern)| glyph.clone().eq(pattern.iter().copied()))
+                .map(|&(letter, _)| letter)
+        })
+        .collect()
+}
+
+fn read_code(coordinates: &HashSet<(usize, usize)>) -> String {
+    let rows = render(coordinates);
+    recognize(&rows).unwrap_or_else(|| format!("\n{}", rows.join("\n")))
+}
+
+pub fn part2() -> String {
+    let (coords, _) = visible_dots_after_folding(&COORDINATES, &FOLD_INSTRUCTIONS).unwrap();
+    read_code(&coords)
+}
+
+pub fn print_folds() -> Result<(), &'static str> {
+    for (step, (coords, fold)) in folding_frames(&COORDINATES, &FOLD_INSTRUCTIONS)?
+        .into_iter()
+        .enumerate()
+    {
+        match fold {
+            Some(fold) => println!("Step {}: {}", step, fold),
+            None => println!("Step {}: done", step),
+        }
+        for row in render_with_fold(&coords, fold) {
+            println!("{}", row);
         }
         println!();
     }
+
+    Ok(())
+}
+
+pub fn export_folds_svg(dir: &str) -> Result<(), &'static str> {
+    let frames = folding_frames(&COORDINATES, &FOLD_INSTRUCTIONS)?;
+    fs::create_dir_all(dir).map_err(|_| "Failed to create the output directory")?;
+    for (step, (coords, fold)) in frames.into_iter().enumerate() {
+        let path = Path::new(dir).join(format!("fold-{:02}.svg", step));
+        fs::write(path, render_svg(&coords, fold)).map_err(|_| "Failed to write an SVG frame")?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -115,17 +382,123 @@ mod tests {
         (8, 10),
         (9, 0),
     ];
-    const FOLD_INSTRUCTIONS: &[(u8, usize)] = &[(b'y', 7), (b'x', 5)];
+    const FOLD_INSTRUCTIONS: &[Fold] = &[
+        Fold {
+            axis: Axis::Y,
+            at: 7,
+        },
+        Fold {
+            axis: Axis::X,
+            at: 5,
+        },
+    ];
 
     #[test]
     fn part1_example() {
         assert_eq!(
-            visible_dots_after_folding(COORDINATES, &FOLD_INSTRUCTIONS[..1]).1,
+            visible_dots_after_folding(COORDINATES, &FOLD_INSTRUCTIONS[..1])
+                .unwrap()
+                .1,
             17
         );
         assert_eq!(
-            visible_dots_after_folding(COORDINATES, FOLD_INSTRUCTIONS).1,
+            visible_dots_after_folding(COORDINATES, FOLD_INSTRUCTIONS)
+                .unwrap()
+                .1,
             16
         );
     }
+
+    #[test]
+    fn part2_example() {
+        let (coords, _) = visible_dots_after_folding(COORDINATES, FOLD_INSTRUCTIONS).unwrap();
+        // The example folds into a square, which isn't a known glyph.
+        assert_eq!(read_code(&coords), "\n#####\n#...#\n#...#\n#...#\n#####");
+    }
+
+    #[test]
+    fn parse_fold() {
+        assert_eq!(
+            "fold along x=655".parse(),
+            Ok(Fold {
+                axis: Axis::X,
+                at: 655
+            })
+        );
+        assert_eq!("fold along z=3".parse::<Fold>(), Err("Not an axis"));
+        assert_eq!("fold along y3".parse::<Fold>(), Err("Missing '='"));
+        assert_eq!("fold along y=".parse::<Fold>(), Err("Not a usize"));
+    }
+
+    #[test]
+    fn uneven_folds() {
+        let fold = Fold {
+            axis: Axis::X,
+            at: 2,
+        };
+
+        // The right part is longer, so the paper extends to the left of the old origin.
+        let (dots, len) = visible_dots_after_folding(&[(0, 0), (3, 0), (7, 1)], &[fold]).unwrap();
+        assert_eq!(len, 3);
+        assert_eq!(dots, HashSet::from([(3, 0), (4, 0), (0, 1)]));
+
+        assert_eq!(
+            visible_dots_after_folding(&[(2, 0), (4, 0)], &[fold]),
+            Err("Fold line goes through a dot")
+        );
+    }
+
+    #[test]
+    fn folding_visualization() {
+        let frames = folding_frames(COORDINATES, FOLD_INSTRUCTIONS).unwrap();
+        assert_eq!(frames.len(), FOLD_INSTRUCTIONS.len() + 1);
+        assert_eq!(frames[1].0.len(), 17);
+
+        let rows = render_with_fold(&frames[1].0, frames[1].1);
+        assert_eq!(
+            rows,
+            [
+                "#.##.|#..#.",
+                "#...#|.....",
+                ".....|#...#",
+                "#...#|.....",
+                ".#.#.|#.###",
+            ]
+        );
+
+        let svg = render_svg(&frames[1].0, frames[1].1);
+        assert_eq!(svg.matches("<rect").count(), 17 + 1);
+        assert!(svg.contains("<line x1=\"55\" y1=\"0\" x2=\"55\" y2=\"50\""));
+    }
+
+    #[test]
+    fn recognize_glyphs() {
+        let rows: Vec<String> = [
+            "#..#.####..##..###..####.#..#.###..###.",
+            "#..#.#....#..#.#..#....#.#.#..#..#.#..#",
+            "####.###..#....#..#...#..##...#..#.#..#",
+            "#..#.#....#....###...#...#.#..###..###.",
+            "#..#.#....#..#.#.#..#....#.#..#....#.#.",
+            "#..#.####..##..#..#.####.#..#.#....#..#",
+        ]
+        .iter()
+        .map(|row| row.to_string())
+        .collect();
+
+        assert_eq!(recognize(&rows).as_deref(), Some("HECRZKPR"));
+
+        let rows: Vec<String> = [
+            "#.....###",
+            "#......#.",
+            "#......#.",
+            "#......#.",
+            "#......#.",
+            "####..###",
+        ]
+        .iter()
+        .map(|row| row.to_string())
+        .collect();
+
+        assert_eq!(recognize(&rows).as_deref(), Some("LI"));
+    }
 }
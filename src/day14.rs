@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use std::io::{self, BufWriter, Write};
 
 use once_cell::sync::Lazy;
 
@@ -22,8 +24,20 @@ fn elements_difference_after_n_steps(
     pair_insertion: &[((u8, u8), u8)],
     n: usize,
 ) -> usize {
+    let element_occurrences = element_counts_after_n_steps(polymer_template, pair_insertion, n);
+    let max = element_occurrences.values().max().unwrap();
+    let min = element_occurrences.values().min().unwrap();
+
+    max - min
+}
+
+fn element_counts_after_n_steps(
+    polymer_template: &[u8],
+    pair_insertion: &[((u8, u8), u8)],
+    n: usize,
+) -> BTreeMap<u8, usize> {
     let mut pair_occurrences = HashMap::<(u8, u8), usize>::new();
-    let mut element_occurrences = HashMap::<u8, usize>::new();
+    let mut element_occurrences = BTreeMap::<u8, usize>::new();
     let rules: HashMap<(u8, u8), u8> = pair_insertion.iter().copied().collect();
 
     for pair in polymer_template.windows(2) {
@@ -42,7 +56,11 @@ fn elements_difference_after_n_steps(
             .collect();
 
         for ((lhs, rhs), count) in keys {
-            let replacement = rules[&(lhs, rhs)];
+            // Pairs without an insertion rule stay as they are.
+            let replacement = match rules.get(&(lhs, rhs)) {
+                Some(&replacement) => replacement,
+                None => continue,
+            };
 
             *pair_occurrences.entry((lhs, replacement)).or_default() += count;
             *pair_occurrences.entry((replacement, rhs)).or_default() += count;
@@ -51,18 +69,212 @@ fn elements_difference_after_n_steps(
         }
     }
 
-    let max = element_occurrences
-        .iter()
-        .map(|(_, &count)| count)
-        .max()
-        .unwrap();
-    let min = element_occurrences
-        .iter()
-        .map(|(_, &count)| count)
-        .min()
-        .unwrap();
+    element_occurrences
+}
 
-    max - min
+// Pairs that show up in the template, or that insertions can create from it, but have no
+// insertion rule.
+fn missing_rules(polymer_template: &[u8], pair_insertion: &[((u8, u8), u8)]) -> Vec<(u8, u8)> {
+    let rules: HashMap<(u8, u8), u8> = pair_insertion.iter().copied().collect();
+    let mut seen = HashSet::new();
+    let mut to_visit: Vec<(u8, u8)> = polymer_template
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect();
+    let mut missing = Vec::new();
+
+    while let Some((lhs, rhs)) = to_visit.pop() {
+        if !seen.insert((lhs, rhs)) {
+            continue;
+        }
+        match rules.get(&(lhs, rhs)) {
+            Some(&replacement) => to_visit.extend([(lhs, replacement), (replacement, rhs)]),
+            None => missing.push((lhs, rhs)),
+        }
+    }
+
+    missing.sort_unstable();
+    missing
+}
+
+// Yields the polymer after `n` steps one element at a time. Each pair of the template is
+// expanded depth-first, so only O(n) pairs are kept around at once.
+struct Polymer<'a> {
+    rules: HashMap<(u8, u8), u8>,
+    template: &'a [u8],
+    position: usize,
+    n: usize,
+    to_expand: Vec<((u8, u8), usize)>,
+}
+
+impl<'a> Polymer<'a> {
+    fn new(polymer_template: &'a [u8], pair_insertion: &[((u8, u8), u8)], n: usize) -> Self {
+        Self {
+            rules: pair_insertion.iter().copied().collect(),
+            template: polymer_template,
+            position: 0,
+            n,
+            to_expand: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for Polymer<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position == 0 {
+            self.position = 1;
+            return self.template.first().copied();
+        }
+
+        loop {
+            let ((lhs, rhs), steps) = match self.to_expand.pop() {
+                Some(entry) => entry,
+                None => {
+                    let pair = self.template.get(self.position - 1..=self.position)?;
+                    self.position += 1;
+                    ((pair[0], pair[1]), self.n)
+                }
+            };
+
+            // Only the right element is emitted, the left one came out right before it.
+            match self.rules.get(&(lhs, rhs)) {
+                Some(&replacement) if steps > 0 => {
+                    self.to_expand.push(((replacement, rhs), steps - 1));
+                    self.to_expand.push(((lhs, replacement), steps - 1));
+                }
+                _ => return Some(rhs),
+            }
+        }
+    }
+}
+
+// Same counts as `element_counts_after_n_steps`, but computed by raising the pair transition
+// matrix to the n-th power, which takes O(log n) matrix multiplications. Counts double with
+// every step, so without a `modulus` anything past about 125 steps fails with an overflow
+// error; large n such as 10^6 only works with a `modulus`. As in `element_counts_after_n_steps`,
+// the last of several rules for the same pair wins.
+fn element_counts_by_matrix(
+    polymer_template: &[u8],
+    pair_insertion: &[((u8, u8), u8)],
+    n: usize,
+    modulus: Option<u64>,
+) -> Result<BTreeMap<u8, u128>, &'static str> {
+    if modulus == Some(0) {
+        return Err("Modulus must be positive");
+    }
+
+    let rules: HashMap<(u8, u8), u8> = pair_insertion.iter().copied().collect();
+    let mut pairs: Vec<(u8, u8)> = Vec::new();
+    let mut index = HashMap::new();
+    let mut record = |pair| {
+        *index.entry(pair).or_insert_with(|| {
+            pairs.push(pair);
+            pairs.len() - 1
+        })
+    };
+
+    for pair in polymer_template.windows(2) {
+        record((pair[0], pair[1]));
+    }
+    for (&(lhs, rhs), &replacement) in &rules {
+        record((lhs, rhs));
+        record((lhs, replacement));
+        record((replacement, rhs));
+    }
+
+    // Without any pairs there is nothing to insert, and the template never changes.
+    if pairs.is_empty() {
+        let mut element_occurrences = BTreeMap::<u8, u128>::new();
+        for &element in polymer_template {
+            *element_occurrences.entry(element).or_default() += 1;
+        }
+        return Ok(element_occurrences);
+    }
+
+    // Row `i` holds how many of each pair a single occurrence of pair `i` turns into. Pairs
+    // without an insertion rule are left untouched.
+    let mut transitions = vec![vec![0; pairs.len()]; pairs.len()];
+    for (i, row) in transitions.iter_mut().enumerate() {
+        row[i] = 1;
+    }
+    for (&(lhs, rhs), &replacement) in &rules {
+        let row = &mut transitions[index[&(lhs, rhs)]];
+        row[index[&(lhs, rhs)]] -= 1;
+        row[index[&(lhs, replacement)]] += 1;
+        row[index[&(replacement, rhs)]] += 1;
+    }
+
+    let mut pair_occurrences = vec![0; pairs.len()];
+    for pair in polymer_template.windows(2) {
+        pair_occurrences[index[&(pair[0], pair[1])]] += 1;
+    }
+
+    let mut n = n;
+    while n > 0 {
+        if n & 1 == 1 {
+            pair_occurrences = multiply(&[pair_occurrences], &transitions, modulus)?.remove(0);
+        }
+        n >>= 1;
+        // Squaring past the last needed power could overflow for no reason.
+        if n > 0 {
+            transitions = multiply(&transitions, &transitions, modulus)?;
+        }
+    }
+
+    // Every element is the left side of exactly one pair, except for the last one, which is
+    // the same as the template's.
+    let mut element_occurrences = BTreeMap::<u8, u128>::new();
+    if let Some(&last) = polymer_template.last() {
+        element_occurrences.insert(last, 1);
+    }
+    for (&(lhs, _), &count) in pairs.iter().zip(&pair_occurrences) {
+        let entry = element_occurrences.entry(lhs).or_default();
+        *entry = mul_add(*entry, count, 1, modulus)?;
+    }
+
+    Ok(element_occurrences)
+}
+
+fn multiply(
+    lhs: &[Vec<u128>],
+    rhs: &[Vec<u128>],
+    modulus: Option<u64>,
+) -> Result<Vec<Vec<u128>>, &'static str> {
+    let mut product = vec![vec![0; rhs[0].len()]; lhs.len()];
+
+    for (i, row) in lhs.iter().enumerate() {
+        for (k, &a) in row.iter().enumerate() {
+            if a == 0 {
+                continue;
+            }
+            for (j, &b) in rhs[k].iter().enumerate() {
+                product[i][j] = mul_add(product[i][j], a, b, modulus)?;
+            }
+        }
+    }
+
+    Ok(product)
+}
+
+// Computes `acc + a * b`, either modulo `modulus` or failing on overflow.
+fn mul_add(acc: u128, a: u128, b: u128, modulus: Option<u64>) -> Result<u128, &'static str> {
+    match modulus {
+        // All operands are below 2^64, so neither the product nor the sum can overflow.
+        Some(m) => {
+            let sum = acc + a * b % m as u128;
+            Ok(if sum >= m as u128 {
+                sum - m as u128
+            } else {
+                sum
+            })
+        }
+        None => a
+            .checked_mul(b)
+            .and_then(|product| acc.checked_add(product))
+            .ok_or("Element count overflows u128, try a modulus"),
+    }
 }
 
 pub fn part1() -> usize {
@@ -75,11 +287,44 @@ pub fn part2() -> usize {
     elements_difference_after_n_steps(polymer_template, pair_insertion, 40)
 }
 
+pub fn print_polymer(n: usize) -> Result<(), String> {
+    let (polymer_template, pair_insertion) = &*INPUT;
+    let missing = missing_rules(polymer_template, pair_insertion);
+    if !missing.is_empty() {
+        let missing: Vec<String> = missing
+            .into_iter()
+            .map(|(lhs, rhs)| format!("{}{}", char::from(lhs), char::from(rhs)))
+            .collect();
+        return Err(format!(
+            "Missing insertion rules for {}",
+            missing.join(", ")
+        ));
+    }
+
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    for element in Polymer::new(polymer_template, pair_insertion, n) {
+        stdout.write_all(&[element]).unwrap();
+    }
+    stdout.write_all(b"\n").unwrap();
+
+    Ok(())
+}
+
+pub fn print_element_counts(n: usize, modulus: Option<u64>) -> Result<(), &'static str> {
+    let (polymer_template, pair_insertion) = &*INPUT;
+    let counts = element_counts_by_matrix(polymer_template, pair_insertion, n, modulus)?;
+    for (element, count) in counts {
+        println!("{}: {}", char::from(element), count);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const POLYMER_TEMPLATE: &[u8] = &[b'N', b'N', b'C', b'B'];
+    const POLYMER_TEMPLATE: &[u8] = b"NNCB";
     const PAIR_INSERTION: &[((u8, u8), u8)] = &[
         ((b'C', b'H'), b'B'),
         ((b'H', b'H'), b'N'),
@@ -114,4 +359,104 @@ mod tests {
             2188189693529
         );
     }
+
+    #[test]
+    fn element_counts() {
+        let counts = element_counts_after_n_steps(POLYMER_TEMPLATE, PAIR_INSERTION, 10);
+        assert_eq!(
+            counts.into_iter().collect::<Vec<_>>(),
+            [(b'B', 1749), (b'C', 298), (b'H', 161), (b'N', 865)]
+        );
+
+        for n in [0, 1, 10, 40] {
+            let counts = element_counts_after_n_steps(POLYMER_TEMPLATE, PAIR_INSERTION, n);
+            let by_matrix =
+                element_counts_by_matrix(POLYMER_TEMPLATE, PAIR_INSERTION, n, None).unwrap();
+            assert!(counts
+                .iter()
+                .all(|(element, &count)| by_matrix[element] == count as u128));
+        }
+    }
+
+    #[test]
+    fn polymer_expansion() {
+        let polymer = |n| {
+            let polymer: Vec<u8> = Polymer::new(POLYMER_TEMPLATE, PAIR_INSERTION, n).collect();
+            String::from_utf8(polymer).unwrap()
+        };
+
+        assert_eq!(polymer(0), "NNCB");
+        assert_eq!(polymer(1), "NCNBCHB");
+        assert_eq!(polymer(2), "NBCCNBBBCBHCB");
+        assert_eq!(
+            polymer(4),
+            "NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB"
+        );
+
+        let mut counts = BTreeMap::new();
+        for element in Polymer::new(POLYMER_TEMPLATE, PAIR_INSERTION, 10) {
+            *counts.entry(element).or_default() += 1;
+        }
+        assert_eq!(
+            counts,
+            element_counts_after_n_steps(POLYMER_TEMPLATE, PAIR_INSERTION, 10)
+        );
+    }
+
+    #[test]
+    fn rule_validation() {
+        assert!(missing_rules(POLYMER_TEMPLATE, PAIR_INSERTION).is_empty());
+        assert_eq!(missing_rules(b"NNCBX", PAIR_INSERTION), [(b'B', b'X')]);
+        assert_eq!(
+            missing_rules(b"NH", &[((b'N', b'H'), b'C')]),
+            [(b'C', b'H'), (b'N', b'C')]
+        );
+    }
+
+    #[test]
+    fn element_counts_with_modulus() {
+        const MODULUS: u64 = 1_000_000_007;
+
+        let exact = element_counts_by_matrix(POLYMER_TEMPLATE, PAIR_INSERTION, 40, None).unwrap();
+        let reduced =
+            element_counts_by_matrix(POLYMER_TEMPLATE, PAIR_INSERTION, 40, Some(MODULUS)).unwrap();
+        assert!(exact
+            .iter()
+            .all(|(element, &count)| reduced[element] == count % MODULUS as u128));
+
+        assert!(element_counts_by_matrix(POLYMER_TEMPLATE, PAIR_INSERTION, 200, None).is_err());
+        let huge =
+            element_counts_by_matrix(POLYMER_TEMPLATE, PAIR_INSERTION, 1_000_000, Some(MODULUS));
+        assert_eq!(huge.unwrap().len(), 4);
+    }
+
+    #[test]
+    fn single_element_template() {
+        for n in [0, 1, 40] {
+            let counts = element_counts_after_n_steps(b"N", &[], n);
+            let by_matrix = element_counts_by_matrix(b"N", &[], n, None).unwrap();
+            assert_eq!(counts.into_iter().collect::<Vec<_>>(), [(b'N', 1)]);
+            assert_eq!(by_matrix.into_iter().collect::<Vec<_>>(), [(b'N', 1)]);
+        }
+        assert!(element_counts_by_matrix(b"", &[], 3, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn duplicated_rules() {
+        let mut rules = PAIR_INSERTION.to_vec();
+        rules.insert(0, ((b'N', b'N'), b'B'));
+        rules.push(((b'C', b'B'), b'N'));
+
+        let counts = element_counts_after_n_steps(POLYMER_TEMPLATE, &rules, 10);
+        let by_matrix = element_counts_by_matrix(POLYMER_TEMPLATE, &rules, 10, None).unwrap();
+        assert!(counts
+            .iter()
+            .all(|(element, &count)| by_matrix[element] == count as u128));
+        assert_ne!(
+            counts,
+            element_counts_after_n_steps(POLYMER_TEMPLATE, PAIR_INSERTION, 10)
+        );
+    }
 }
@@ -111,7 +111,7 @@ pub fn part2() -> usize {
 fn expand_matrix(matrix: &[&[u8]]) -> Vec<Vec<u8>> {
     let original_width = matrix[0].len();
     let original_height = matrix.len();
-    let expanded: Vec<&[u8]> = matrix.iter().copied().collect();
+    let expanded: Vec<&[u8]> = matrix.to_vec();
     let expanded = expanded.repeat(5);
     let mut expanded: Vec<_> = expanded.iter().map(|&row| row.repeat(5)).collect();
 
@@ -1,71 +1,150 @@
-use std::fmt::Debug;
-use std::ops::Add;
+use std::fmt::{self, Display};
+use std::iter::Sum;
+use std::ops::{Add, Range};
+use std::str::FromStr;
+use std::thread;
+use std::time::Instant;
 
 use once_cell::sync::Lazy;
 
 use crate::utils;
 
-static INPUT: Lazy<Vec<List>> = Lazy::new(|| utils::get_input_as_vec_with(INPUT_PATH, List::parse));
+static INPUT: Lazy<Vec<SnailfishNumber>> = Lazy::new(|| utils::get_input_as_vec(INPUT_PATH));
 const INPUT_PATH: &str = "input/day18";
 
-#[derive(Clone)]
-struct List {
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SnailfishNumber {
     inner: Elem,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Elem {
     Number(usize),
     Pair { lhs: Box<Elem>, rhs: Box<Elem> },
 }
 
-impl List {
-    fn parse(mut p: &str) -> Self {
-        Self {
-            inner: Self::elem(&mut p),
+impl FromStr for SnailfishNumber {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut s = s.trim();
+        if !s.starts_with('[') {
+            return Err("A snailfish number must be a pair");
+        }
+
+        let inner = Self::elem(&mut s)?;
+        if !s.is_empty() {
+            return Err("Unexpected characters after the number");
         }
+
+        Ok(Self { inner })
     }
+}
 
-    fn elem(e: &mut &str) -> Elem {
-        let ch = &e[..1];
-        match ch {
-            "[" => {
-                let (lhs, rhs) = Self::pair(e);
-                Elem::Pair {
+impl SnailfishNumber {
+    fn elem(e: &mut &str) -> Result<Elem, &'static str> {
+        match e.bytes().next() {
+            Some(b'[') => {
+                let (lhs, rhs) = Self::pair(e)?;
+                Ok(Elem::Pair {
                     lhs: Box::new(lhs),
                     rhs: Box::new(rhs),
-                }
+                })
             }
-            _ => Self::number(e),
+            Some(b'0'..=b'9') => Self::number(e),
+            Some(_) => Err("Expected a pair or a number"),
+            None => Err("Unexpected end of input"),
         }
     }
 
-    fn pair(p: &mut &str) -> (Elem, Elem) {
-        eat(p, "[");
-        let lhs = Self::elem(p);
-        eat(p, ",");
-        let rhs = Self::elem(p);
-        eat(p, "]");
+    fn pair(p: &mut &str) -> Result<(Elem, Elem), &'static str> {
+        eat(p, "[")?;
+        let lhs = Self::elem(p)?;
+        eat(p, ",")?;
+        let rhs = Self::elem(p)?;
+        eat(p, "]")?;
 
-        (lhs, rhs)
+        Ok((lhs, rhs))
     }
 
-    fn number(n: &mut &str) -> Elem {
-        let num = &n[..1];
-        eat(n, num);
-        Elem::Number(num.parse().unwrap())
+    fn number(n: &mut &str) -> Result<Elem, &'static str> {
+        let len = n.bytes().take_while(u8::is_ascii_digit).count();
+        let num = n[..len].parse().map_err(|_| "Number too large")?;
+        *n = &n[len..];
+
+        Ok(Elem::Number(num))
     }
 
     fn reduce(&mut self) {
-        loop {
-            if self.inner.more_than_four_nestings(0).is_none() && !self.inner.split() {
-                break;
-            }
+        while self.reduce_once().is_some() {}
+    }
+
+    // Applies the first applicable action, if any.
+    fn reduce_once(&mut self) -> Option<Action> {
+        if self.inner.more_than_four_nestings(0).is_some() {
+            Some(Action::Explode)
+        } else if self.inner.split() {
+            Some(Action::Split)
+        } else {
+            None
         }
     }
 
-    fn magnitude(&self) -> usize {
-        self.inner.magnitude()
+    fn reduction_steps(self) -> ReductionSteps {
+        ReductionSteps { current: self }
+    }
+
+    // Pairs up both numbers without reducing the result.
+    fn join(self, rhs: Self) -> Self {
+        Self {
+            inner: Elem::Pair {
+                lhs: Box::new(self.inner),
+                rhs: Box::new(rhs.inner),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Explode,
+    Split,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReductionStep {
+    action: Action,
+    before: SnailfishNumber,
+    after: SnailfishNumber,
+}
+
+// Same format as the listing in the puzzle's description.
+impl Display for ReductionStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.action {
+            Action::Explode => write!(f, "after explode:  {}", self.after),
+            Action::Split => write!(f, "after split:    {}", self.after),
+        }
+    }
+}
+
+// Yields every action taken while reducing a number, in order.
+struct ReductionSteps {
+    current: SnailfishNumber,
+}
+
+impl Iterator for ReductionSteps {
+    type Item = ReductionStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let before = self.current.clone();
+        let action = self.current.reduce_once()?;
+
+        Some(ReductionStep {
+            action,
+            before,
+            after: self.current.clone(),
+        })
     }
 }
 
@@ -81,14 +160,20 @@ enum ReduceOp<'p> {
 }
 
 impl Elem {
-    fn more_than_four_nestings(&mut self, count: usize) -> Option<ReduceOp> {
+    fn more_than_four_nestings(&mut self, count: usize) -> Option<ReduceOp<'_>> {
         match self {
             Self::Number(_) => None,
             Self::Pair {
                 lhs: outer_lhs,
                 rhs: outer_rhs,
             } => {
-                if count == 4 {
+                // Numbers that were never reduced can nest pairs even deeper, in which case the
+                // leftmost pair of two regular numbers below the fourth level explodes.
+                let regular = matches!(
+                    (&**outer_lhs, &**outer_rhs),
+                    (Elem::Number(_), Elem::Number(_))
+                );
+                if count >= 4 && regular {
                     Some(ReduceOp::Pair {
                         lhs: outer_lhs,
                         rhs: outer_rhs,
@@ -97,7 +182,7 @@ impl Elem {
                     match outer_lhs.more_than_four_nestings(count + 1) {
                         Some(ReduceOp::Pair { lhs, rhs }) => {
                             let (lhs, rhs) = (lhs.unwrap_number(), rhs.unwrap_number());
-                            *outer_lhs = Box::new(Elem::Number(0));
+                            **outer_lhs = Elem::Number(0);
                             *outer_rhs.leftmost_number() += rhs;
 
                             return Some(ReduceOp::Explode {
@@ -117,7 +202,7 @@ impl Elem {
                     match outer_rhs.more_than_four_nestings(count + 1) {
                         Some(ReduceOp::Pair { lhs, rhs }) => {
                             let (lhs, rhs) = (lhs.unwrap_number(), rhs.unwrap_number());
-                            *outer_rhs = Box::new(Elem::Number(0));
+                            **outer_rhs = Elem::Number(0);
                             *outer_lhs.rightmost_number() += lhs;
 
                             Some(ReduceOp::Explode {
@@ -144,8 +229,8 @@ impl Elem {
                 let num = *num;
                 if num >= 10 {
                     *self = Elem::Pair {
-                        lhs: Box::new(Elem::Number((num as f32 / 2.0).floor() as usize)),
-                        rhs: Box::new(Elem::Number((num as f32 / 2.0).ceil() as usize)),
+                        lhs: Box::new(Elem::Number(num / 2)),
+                        rhs: Box::new(Elem::Number(num - num / 2)),
                     };
                     true
                 } else {
@@ -185,82 +270,325 @@ impl Elem {
     }
 }
 
-impl Add for List {
+// Adding two numbers also reduces the result.
+impl Add for SnailfishNumber {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            inner: Elem::Pair {
-                lhs: Box::new(self.inner),
-                rhs: Box::new(rhs.inner),
-            },
-        }
+        let mut sum = self.join(rhs);
+        sum.reduce();
+        sum
+    }
+}
+
+// There's no zero snailfish number, so summing an empty iterator gives None.
+impl Sum<SnailfishNumber> for Option<SnailfishNumber> {
+    fn sum<I: Iterator<Item = SnailfishNumber>>(iter: I) -> Self {
+        iter.reduce(Add::add)
     }
 }
 
-fn eat(s: &mut &str, tok: &str) {
-    debug_assert_eq!(&s[..tok.len()], tok);
-    *s = &s[tok.len()..];
+fn eat(s: &mut &str, tok: &'static str) -> Result<(), &'static str> {
+    *s = s.strip_prefix(tok).ok_or(match tok {
+        "[" => "Expected '['",
+        "," => "Expected ','",
+        _ => "Expected ']'",
+    })?;
+
+    Ok(())
 }
 
-impl Debug for List {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Display for SnailfishNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.inner.fmt(f)
     }
 }
 
-impl Debug for Elem {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Display for Elem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Elem::Number(n) => f.write_fmt(format_args!("{}", *n)),
-            Elem::Pair { lhs, rhs } => f.write_fmt(format_args!(
-                "[{},{}]",
-                format_args!("{:?}", &*lhs),
-                format_args!("{:?}", &*rhs),
-            )),
+            Elem::Number(n) => write!(f, "{}", n),
+            Elem::Pair { lhs, rhs } => write!(f, "[{},{}]", lhs, rhs),
         }
     }
 }
 
-fn add_all(pairs: Vec<List>) -> List {
-    pairs
-        .into_iter()
-        .reduce(|prev, curr| {
-            let mut merged = prev + curr;
-            merged.reduce();
-            merged
-        })
-        .unwrap()
+trait Magnitude {
+    fn magnitude(&self) -> usize;
+}
+
+impl Magnitude for SnailfishNumber {
+    fn magnitude(&self) -> usize {
+        self.inner.magnitude()
+    }
+}
+
+// Alternative representation that keeps only the regular numbers, from left to right, along
+// with how many pairs each one is nested in. Explosions and splits happen in place without
+// walking a tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FlatSnailfishNumber {
+    numbers: Vec<(usize, usize)>,
+}
+
+impl FlatSnailfishNumber {
+    fn reduce(&mut self) {
+        while self.explode() || self.split() {}
+    }
+
+    // The first two consecutive numbers at the same depth always form a pair. Checking both
+    // depths finds the leftmost pair of two regular numbers even in numbers that were never
+    // reduced and nest deeper than five pairs.
+    fn explode(&mut self) -> bool {
+        let i = match self
+            .numbers
+            .windows(2)
+            .position(|w| w[0].1 > 4 && w[0].1 == w[1].1)
+        {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let ((lhs, depth), (rhs, _)) = (self.numbers[i], self.numbers[i + 1]);
+        if i > 0 {
+            self.numbers[i - 1].0 += lhs;
+        }
+        if let Some((next, _)) = self.numbers.get_mut(i + 2) {
+            *next += rhs;
+        }
+        self.numbers[i] = (0, depth - 1);
+        self.numbers.remove(i + 1);
+
+        true
+    }
+
+    fn split(&mut self) -> bool {
+        let i = match self.numbers.iter().position(|&(num, _)| num >= 10) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let (num, depth) = self.numbers[i];
+        self.numbers[i] = (num / 2, depth + 1);
+        self.numbers.insert(i + 1, (num - num / 2, depth + 1));
+
+        true
+    }
+}
+
+impl Magnitude for FlatSnailfishNumber {
+    // Two consecutive numbers at the same depth always form a pair, so they can be collapsed
+    // into their magnitude one level up until a single number is left.
+    fn magnitude(&self) -> usize {
+        let mut stack: Vec<(usize, usize)> = Vec::with_capacity(self.numbers.len());
+
+        for &(mut num, mut depth) in &self.numbers {
+            while let Some(&(lhs, lhs_depth)) = stack.last() {
+                if lhs_depth != depth {
+                    break;
+                }
+                stack.pop();
+                num = 3 * lhs + 2 * num;
+                depth -= 1;
+            }
+            stack.push((num, depth));
+        }
+
+        stack[0].0
+    }
+}
+
+impl From<&SnailfishNumber> for FlatSnailfishNumber {
+    fn from(number: &SnailfishNumber) -> Self {
+        fn flatten(elem: &Elem, depth: usize, numbers: &mut Vec<(usize, usize)>) {
+            match elem {
+                Elem::Number(num) => numbers.push((*num, depth)),
+                Elem::Pair { lhs, rhs } => {
+                    flatten(lhs, depth + 1, numbers);
+                    flatten(rhs, depth + 1, numbers);
+                }
+            }
+        }
+
+        let mut numbers = Vec::new();
+        flatten(&number.inner, 0, &mut numbers);
+
+        Self { numbers }
+    }
 }
 
-fn largest_magnitude(pairs: Vec<List>) -> usize {
-    let mut max = 0;
+impl From<&FlatSnailfishNumber> for SnailfishNumber {
+    fn from(number: &FlatSnailfishNumber) -> Self {
+        fn unflatten(depth: usize, numbers: &[(usize, usize)], next: &mut usize) -> Elem {
+            let (num, num_depth) = numbers[*next];
+            if num_depth == depth {
+                *next += 1;
+                Elem::Number(num)
+            } else {
+                Elem::Pair {
+                    lhs: Box::new(unflatten(depth + 1, numbers, next)),
+                    rhs: Box::new(unflatten(depth + 1, numbers, next)),
+                }
+            }
+        }
 
-    for i in 0..pairs.len() {
+        Self {
+            inner: unflatten(0, &number.numbers, &mut 0),
+        }
+    }
+}
+
+impl FromStr for FlatSnailfishNumber {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(&s.parse::<SnailfishNumber>()?))
+    }
+}
+
+impl Display for FlatSnailfishNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        SnailfishNumber::from(self).fmt(f)
+    }
+}
+
+impl Add for FlatSnailfishNumber {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self.numbers.extend(rhs.numbers);
+        for (_, depth) in &mut self.numbers {
+            *depth += 1;
+        }
+        self.reduce();
+        self
+    }
+}
+
+// The ordered pair of numbers whose sum has the largest magnitude.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LargestSum<N> {
+    lhs: usize,
+    rhs: usize,
+    sum: N,
+    magnitude: usize,
+}
+
+fn largest_magnitude<N>(pairs: &[N]) -> usize
+where
+    N: Clone + Add<Output = N> + Magnitude + Send + Sync,
+{
+    largest_sum(pairs).map_or(0, |largest| largest.magnitude)
+}
+
+// Each thread tries every `rhs` for its own chunk of `lhs` indices. Ties are broken in favour of
+// the smallest `(lhs, rhs)`, so the result doesn't depend on the number of threads.
+fn largest_sum<N>(pairs: &[N]) -> Option<LargestSum<N>>
+where
+    N: Clone + Add<Output = N> + Magnitude + Send + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = pairs.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..pairs.len())
+            .step_by(chunk_size)
+            .map(|from| {
+                let to = (from + chunk_size).min(pairs.len());
+                scope.spawn(move || largest_sum_in(pairs, from..to))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .reduce(|best, curr| {
+                if curr.magnitude > best.magnitude {
+                    curr
+                } else {
+                    best
+                }
+            })
+    })
+}
+
+fn largest_sum_in<N>(pairs: &[N], lhs: Range<usize>) -> Option<LargestSum<N>>
+where
+    N: Clone + Add<Output = N> + Magnitude,
+{
+    let mut best: Option<LargestSum<N>> = None;
+
+    for i in lhs {
         for j in 0..pairs.len() {
             if i == j {
                 continue;
             }
 
-            let mut merged = pairs[i].clone() + pairs[j].clone();
-            merged.reduce();
-            let magnitude = merged.magnitude();
-            if magnitude > max {
-                max = magnitude;
+            let sum = pairs[i].clone() + pairs[j].clone();
+            let magnitude = sum.magnitude();
+            if best.as_ref().is_none_or(|best| magnitude > best.magnitude) {
+                best = Some(LargestSum {
+                    lhs: i,
+                    rhs: j,
+                    sum,
+                    magnitude,
+                });
             }
         }
     }
 
-    max
+    best
 }
 
 pub fn part1() -> usize {
-    let final_list = add_all(INPUT.clone());
-    final_list.magnitude()
+    let sum: Option<SnailfishNumber> = INPUT.iter().cloned().sum();
+    sum.unwrap().magnitude()
 }
 
 pub fn part2() -> usize {
-    largest_magnitude(INPUT.clone())
+    let flat: Vec<FlatSnailfishNumber> = INPUT.iter().map(FlatSnailfishNumber::from).collect();
+    largest_magnitude(&flat)
+}
+
+pub fn print_largest_sum() {
+    let flat: Vec<FlatSnailfishNumber> = INPUT.iter().map(FlatSnailfishNumber::from).collect();
+    let largest = largest_sum(&flat).unwrap();
+    println!(
+        "Line {} + line {} = {} (magnitude {})",
+        largest.lhs + 1,
+        largest.rhs + 1,
+        largest.sum,
+        largest.magnitude
+    );
+}
+
+pub fn bench_part2(rounds: u32) {
+    let flat: Vec<FlatSnailfishNumber> = INPUT.iter().map(FlatSnailfishNumber::from).collect();
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        largest_magnitude(&INPUT);
+    }
+    let tree = start.elapsed() / rounds;
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        largest_magnitude(&flat);
+    }
+    let flat = start.elapsed() / rounds;
+
+    println!("Day 18 [part 02] tree: {:?}/iter", tree);
+    println!("Day 18 [part 02] flat: {:?}/iter", flat);
+}
+
+pub fn print_reduction(lhs: &str, rhs: &str) -> Result<(), &'static str> {
+    let sum = lhs.parse::<SnailfishNumber>()?.join(rhs.parse()?);
+    println!("after addition: {}", sum);
+    for step in sum.reduction_steps() {
+        println!("{}", step);
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -282,19 +610,48 @@ mod tests {
         "[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]",
     ];
 
-    static PAIRS: Lazy<Vec<List>> = Lazy::new(|| INPUT.iter().map(|p| List::parse(p)).collect());
+    static PAIRS: Lazy<Vec<SnailfishNumber>> =
+        Lazy::new(|| INPUT.iter().map(|p| p.parse().unwrap()).collect());
+
+    fn parse(s: &str) -> SnailfishNumber {
+        s.parse().unwrap()
+    }
 
     #[test]
     fn parse_and_add() {
         for (i, pair) in PAIRS.iter().enumerate() {
-            assert_eq!(format!("{:?}", pair), String::from(INPUT[i]));
+            assert_eq!(format!("{}", pair), String::from(INPUT[i]));
         }
     }
 
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("[10,[200,3]]").to_string(), "[10,[200,3]]");
+        assert_eq!(parse("[[1,2],3]").magnitude(), 3 * (3 + 4) + 2 * 3);
+        assert_eq!(
+            "7".parse::<SnailfishNumber>(),
+            Err("A snailfish number must be a pair")
+        );
+        assert_eq!("[1,2".parse::<SnailfishNumber>(), Err("Expected ']'"));
+        assert_eq!("[1;2]".parse::<SnailfishNumber>(), Err("Expected ','"));
+        assert_eq!(
+            "[1,]".parse::<SnailfishNumber>(),
+            Err("Expected a pair or a number")
+        );
+        assert_eq!(
+            "[1,2]]".parse::<SnailfishNumber>(),
+            Err("Unexpected characters after the number")
+        );
+        assert_eq!(
+            "[".parse::<SnailfishNumber>(),
+            Err("Unexpected end of input")
+        );
+    }
+
     #[test]
     fn add_pairs() {
         assert_eq!(
-            format!("{:?}", List::parse("[1,2]") + List::parse("[[3,4],5]")),
+            format!("{}", parse("[1,2]") + parse("[[3,4],5]")),
             String::from("[[1,2],[[3,4],5]]")
         );
     }
@@ -312,36 +669,107 @@ mod tests {
         ];
 
         for (original, exploded) in input {
-            let mut pair = List::parse(original);
+            let mut pair = parse(original);
             pair.reduce();
 
-            assert_eq!(format!("{:?}", pair), String::from(exploded));
+            assert_eq!(format!("{}", pair), String::from(exploded));
         }
     }
 
+    #[test]
+    fn deeply_nested_explosion() {
+        let mut pair = parse("[[[[[[1,2],3],4],5],6],7]");
+        assert_eq!(pair.reduce_once(), Some(Action::Explode));
+        assert_eq!(pair.to_string(), "[[[[[0,5],4],5],6],7]");
+
+        let sum = parse("[[[[[1,2],3],4],5],6]") + parse("[1,1]");
+        assert_eq!(sum.to_string(), "[[[[0,9],5],6],[1,1]]");
+    }
+
     #[test]
     fn reduce() {
-        let mut pair = List::parse("[[[[4,3],4],4],[7,[[8,4],9]]]") + List::parse("[1,1]");
+        let mut pair = parse("[[[[4,3],4],4],[7,[[8,4],9]]]") + parse("[1,1]");
         pair.reduce();
         assert_eq!(
-            format!("{:?}", pair),
+            format!("{}", pair),
             String::from("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]")
         );
     }
 
+    #[test]
+    fn reduction_trace() {
+        let sum = parse("[[[[4,3],4],4],[7,[[8,4],9]]]").join(parse("[1,1]"));
+        let steps: Vec<_> = sum.clone().reduction_steps().collect();
+
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "after explode:  [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[15,[0,13]]],[1,1]]",
+                "after split:    [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+                "after split:    [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+                "after explode:  [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+            ]
+        );
+        assert_eq!(steps[0].before, sum);
+        assert!(steps.windows(2).all(|w| w[0].after == w[1].before));
+        assert_eq!(steps[1].action, Action::Explode);
+        assert_eq!(steps[2].action, Action::Split);
+    }
+
+    #[test]
+    fn flat_representation() {
+        for input in INPUT {
+            let flat: FlatSnailfishNumber = input.parse().unwrap();
+            assert_eq!(flat.to_string(), *input);
+            assert_eq!(flat.magnitude(), parse(input).magnitude());
+        }
+
+        let sum = "[[[[4,3],4],4],[7,[[8,4],9]]]"
+            .parse::<FlatSnailfishNumber>()
+            .unwrap()
+            + "[1,1]".parse().unwrap();
+        assert_eq!(sum.to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+
+        // After the addition, 1 and 2 are adjacent and nested deeper than four pairs, but they
+        // sit at different depths and don't form a pair.
+        let deep = "[[[[1,[2,[3,4]]],5],6],7]";
+        let sum = deep.parse::<FlatSnailfishNumber>().unwrap() + "[1,1]".parse().unwrap();
+        assert_eq!(sum.to_string(), "[[[[0,9],6],7],[1,1]]");
+        assert_eq!(sum.to_string(), (parse(deep) + parse("[1,1]")).to_string());
+
+        let flat: Vec<FlatSnailfishNumber> = PAIRS.iter().map(FlatSnailfishNumber::from).collect();
+        let sum = flat.iter().cloned().reduce(Add::add).unwrap();
+        assert_eq!(sum.magnitude(), 4140);
+        assert_eq!(largest_magnitude(&flat), 3993);
+    }
+
     #[test]
     fn part1_example() {
-        let final_list = add_all(PAIRS.clone());
+        let final_list: SnailfishNumber = PAIRS.iter().cloned().sum::<Option<_>>().unwrap();
 
         assert_eq!(
-            format!("{:?}", final_list),
+            format!("{}", final_list),
             String::from("[[[[6,6],[7,6]],[[7,7],[7,0]]],[[[7,7],[7,7]],[[7,8],[9,9]]]]")
         );
         assert_eq!(final_list.magnitude(), 4140);
+        assert_eq!(PAIRS[..0].iter().cloned().sum::<Option<_>>(), None);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(largest_magnitude(PAIRS.clone()), 3993);
+        assert_eq!(largest_magnitude(&PAIRS), 3993);
+
+        let largest = largest_sum(&PAIRS).unwrap();
+        assert_eq!((largest.lhs, largest.rhs), (8, 0));
+        assert_eq!(
+            largest.sum.to_string(),
+            "[[[[7,8],[6,6]],[[6,0],[7,7]]],[[[7,8],[8,8]],[[7,9],[0,6]]]]"
+        );
+        assert_eq!(largest.magnitude, 3993);
+        assert_eq!(largest_sum_in(&PAIRS, 0..PAIRS.len()), Some(largest));
     }
 }
@@ -1,4 +1,8 @@
-use std::collections::VecDeque;
+use std::fmt::{self, Display, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
 
 use once_cell::sync::Lazy;
 
@@ -12,158 +16,273 @@ static INPUT: Lazy<([u8; 512], Vec<Vec<u8>>)> = Lazy::new(|| {
 });
 const INPUT_PATH: &str = "input/day20";
 
-struct Image {
-    image: Vec<Vec<u8>>,
+// An infinite grid of pixels, where everything outside the bounds has the same value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InfiniteImage {
+    // `pixels[0][0]` is at row `top` and column `left`.
+    pixels: Vec<Vec<u8>>,
+    top: isize,
+    left: isize,
+    background: u8,
 }
 
-struct Margin {
-    top: usize,
-    bottom: usize,
-    left: usize,
-    right: usize,
-}
+impl InfiniteImage {
+    fn new(image: &[&[u8]]) -> Self {
+        let pixels = image
+            .iter()
+            .map(|row| row.iter().map(|&pixel| (pixel == b'#') as u8).collect())
+            .collect();
 
-impl Image {
-    fn enhance(original: &[&[u8]], enhancement: [u8; 512], scale: usize) -> Self {
-        let original_width = original[0].len();
-        let original_height = original.len();
-        let padding = scale * 2 + 1;
-        let width = original[0].len() + padding + 1;
-        let height = original.len() + padding + 1;
-        let mut image = vec![vec![0u8; width]; height];
-        let enhancement = enhancement.map(|pixel| if pixel == b'#' { 1 } else { 0 });
-        let mut to_update = VecDeque::with_capacity(width * 2);
-
-        for i in 0..original_height {
-            for j in 0..original_width {
-                if original[i][j] == b'#' {
-                    image[i + scale + 1][j + scale + 1] = 1;
-                }
+        Self {
+            pixels,
+            top: 0,
+            left: 0,
+            background: 0,
+        }
+    }
+
+    // Same result as calling `enhance` `rounds` times, but all rounds run on two flat buffers
+    // that are already large enough for the final image, so nothing is allocated in between.
+    fn enhance_n(image: &[&[u8]], enhancement: &[u8; 512], rounds: usize) -> Self {
+        let enhancement = enhancement.map(|pixel| (pixel == b'#') as u8);
+        // One extra pixel on every side only ever holds background, so windows never need
+        // bounds checks.
+        let padding = rounds + 1;
+        let height = image.len() + 2 * padding;
+        let width = image.first().map_or(0, |row| row.len()) + 2 * padding;
+        let mut current = vec![0; height * width];
+        let mut next = vec![0; height * width];
+        let mut background = 0;
+
+        for (i, row) in image.iter().enumerate() {
+            for (j, &pixel) in row.iter().enumerate() {
+                current[(i + padding) * width + j + padding] = (pixel == b'#') as u8;
             }
         }
 
-        for s in 0..scale {
-            let margin = Margin {
-                top: scale - s,
-                bottom: original_height + scale + s + 1,
-                left: scale - s,
-                right: original_width + scale + s + 1,
-            };
-
-            for i in scale - s..=original_height + scale + s + 1 {
-                for j in scale - s..=original_width + scale + s + 1 {
-                    let enhanced = Self::enhanced_pixel(&image, (i, j), &enhancement, &margin, s);
-
-                    to_update.push_back((enhanced, (i, j)));
-
-                    // Try to update outstanding pixels.
-                    loop {
-                        if let Some(&(_, pos)) = to_update.front() {
-                            if Self::can_update((i, j), pos) {
-                                let (pixel, (i, j)) = to_update.pop_front().unwrap();
-                                image[i][j] = pixel;
-                            } else {
-                                break;
-                            }
-                        }
+        for _ in 0..rounds {
+            background = enhancement[if background == 0 { 0 } else { 511 }];
+            next.fill(background);
+
+            for i in 1..height - 1 {
+                let up = &current[(i - 1) * width..i * width];
+                let middle = &current[i * width..(i + 1) * width];
+                let down = &current[(i + 1) * width..(i + 2) * width];
+                let row = &mut next[i * width..(i + 1) * width];
+
+                // Slide the 3x3 window right by dropping its left column and shifting in the
+                // next one.
+                let mut pixel_index = 0;
+                for j in 0..width {
+                    pixel_index = (pixel_index << 1) & 0b110_110_110
+                        | (up[j] as usize) << 6
+                        | (middle[j] as usize) << 3
+                        | down[j] as usize;
+                    if j >= 2 {
+                        row[j - 1] = enhancement[pixel_index];
                     }
                 }
             }
 
-            // Update outstanding pixels.
-            while let Some((pixel, (i, j))) = to_update.pop_front() {
-                image[i][j] = pixel;
-            }
+            std::mem::swap(&mut current, &mut next);
         }
 
-        Self { image }
+        let pixels = current
+            .chunks(width)
+            .skip(1)
+            .take(height - 2)
+            .map(|row| row[1..width - 1].to_vec())
+            .collect();
+
+        Self {
+            pixels,
+            top: -(rounds as isize),
+            left: -(rounds as isize),
+            background,
+        }
     }
 
-    fn enhanced_pixel(
-        image: &[Vec<u8>],
-        origin: (usize, usize),
-        enhancement: &[u8; 512],
-        margin: &Margin,
-        round: usize,
-    ) -> u8 {
-        let (i, j) = origin;
+    // Pixels right outside the bounds can see the image, so the result grows by one pixel on
+    // every side. Everything beyond that only sees background, which becomes either the first
+    // or the last entry of the enhancement algorithm.
+    fn enhance(&self, enhancement: &[u8; 512]) -> Self {
+        let (top, left) = (self.top - 1, self.left - 1);
+        let height = self.pixels.len() + 2;
+        let width = self.pixels.first().map_or(0, Vec::len) + 2;
+
+        let pixels = (0..height as isize)
+            .map(|i| {
+                (0..width as isize)
+                    .map(|j| self.enhanced_pixel((top + i, left + j), enhancement))
+                    .collect()
+            })
+            .collect();
+        let background = if self.background == 0 {
+            enhancement[0]
+        } else {
+            enhancement[511]
+        };
+
+        Self {
+            pixels,
+            top,
+            left,
+            background: (background == b'#') as u8,
+        }
+    }
+
+    fn enhanced_pixel(&self, (i, j): (isize, isize), enhancement: &[u8; 512]) -> u8 {
         let mut pixel_index = 0;
-        let mut bit_idx = 8;
 
-        #[allow(clippy::needless_range_loop)]
         for x in i - 1..=i + 1 {
             for y in j - 1..=j + 1 {
-                let neighbor = if x <= margin.top
-                    || x >= margin.bottom
-                    || y <= margin.left
-                    || y >= margin.right
-                {
-                    Self::margin(enhancement, round)
-                } else {
-                    image[x][y]
-                };
-                let neighbor = neighbor as u16;
-                debug_assert!(neighbor == 0 || neighbor == 1);
-                pixel_index |= neighbor << bit_idx;
-                bit_idx -= 1;
+                pixel_index = pixel_index << 1 | self.pixel((x, y)) as usize;
             }
         }
 
-        enhancement[pixel_index as usize]
+        (enhancement[pixel_index] == b'#') as u8
     }
 
-    fn margin(enhancement: &[u8; 512], round: usize) -> u8 {
-        match enhancement[0] {
-            0 => 0,
-            1 => {
-                if round == 0 {
-                    0
-                } else if round % 2 != 0 {
-                    enhancement[0]
-                } else {
-                    enhancement[enhancement.len() - 1]
-                }
-            }
-            _ => unreachable!(),
+    fn pixel(&self, (i, j): (isize, isize)) -> u8 {
+        let (i, j) = (i - self.top, j - self.left);
+        if i < 0 || j < 0 {
+            return self.background;
         }
-    }
 
-    fn can_update(curr_pos: (usize, usize), to_update_pos: (usize, usize)) -> bool {
-        curr_pos.0 > to_update_pos.0 + 1
+        self.pixels
+            .get(i as usize)
+            .and_then(|row| row.get(j as usize))
+            .copied()
+            .unwrap_or(self.background)
     }
 
-    fn lit_pixels(&self) -> usize {
-        self.image
+    fn lit_pixels(&self) -> Result<usize, &'static str> {
+        if self.background == 1 {
+            return Err("Infinitely many pixels are lit");
+        }
+
+        Ok(self
+            .pixels
             .iter()
             .flatten()
             .filter(|&&pixel| pixel == 1)
-            .count()
+            .count())
+    }
+
+    // Plain PBM of the area starting at `(top, left)`, with lit pixels in black. Using the same
+    // area for every round keeps the frames aligned.
+    fn to_pbm(&self, (top, left): (isize, isize), height: usize, width: usize) -> String {
+        let mut pbm = format!("P1\n{} {}\n", width, height);
+
+        for i in top..top + height as isize {
+            let row: Vec<String> = (left..left + width as isize)
+                .map(|j| self.pixel((i, j)).to_string())
+                .collect();
+            pbm.push_str(&row.join(" "));
+            pbm.push('\n');
+        }
+
+        pbm
     }
 }
 
+// Shows the pixels inside the bounds, using the same format as the input.
+impl Display for InfiniteImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.pixels {
+            for &pixel in row {
+                f.write_char(if pixel == 1 { '#' } else { '.' })?;
+            }
+            f.write_char('\n')?;
+        }
+
+        Ok(())
+    }
+}
+
+fn enhancement_rounds(
+    image: &[&[u8]],
+    enhancement: &[u8; 512],
+    rounds: usize,
+) -> Vec<InfiniteImage> {
+    let mut images = vec![InfiniteImage::new(image)];
+    for _ in 0..rounds {
+        images.push(images.last().unwrap().enhance(enhancement));
+    }
+
+    images
+}
+
 pub fn part1() -> usize {
     let (enhancement, image) = &*INPUT;
     let image: Vec<&[u8]> = image.iter().map(|e| e.as_ref()).collect();
-    Image::enhance(&image, *enhancement, 2).lit_pixels()
+    InfiniteImage::enhance_n(&image, enhancement, 2)
+        .lit_pixels()
+        .unwrap()
 }
 
 pub fn part2() -> usize {
     let (enhancement, image) = &*INPUT;
     let image: Vec<&[u8]> = image.iter().map(|e| e.as_ref()).collect();
-    Image::enhance(&image, *enhancement, 50).lit_pixels()
+    InfiniteImage::enhance_n(&image, enhancement, 50)
+        .lit_pixels()
+        .unwrap()
+}
+
+pub fn bench_part2(rounds: u32) {
+    let (enhancement, image) = &*INPUT;
+    let image: Vec<&[u8]> = image.iter().map(|e| e.as_ref()).collect();
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        (0..50).fold(InfiniteImage::new(&image), |image, _| {
+            image.enhance(enhancement)
+        });
+    }
+    let per_round = start.elapsed() / rounds;
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        InfiniteImage::enhance_n(&image, enhancement, 50);
+    }
+    let double_buffered = start.elapsed() / rounds;
+
+    println!("Day 20 [part 02] per round: {:?}/iter", per_round);
+    println!(
+        "Day 20 [part 02] double buffered: {:?}/iter",
+        double_buffered
+    );
+}
+
+pub fn print_image(rounds: usize) {
+    let (enhancement, image) = &*INPUT;
+    let image: Vec<&[u8]> = image.iter().map(|e| e.as_ref()).collect();
+    print!("{}", InfiniteImage::enhance_n(&image, enhancement, rounds));
+}
+
+pub fn export_rounds(dir: &str, rounds: usize) -> io::Result<()> {
+    let (enhancement, image) = &*INPUT;
+    let image: Vec<&[u8]> = image.iter().map(|e| e.as_ref()).collect();
+    let images = enhancement_rounds(&image, enhancement, rounds);
+
+    let last = images.last().unwrap();
+    let origin = (last.top, last.left);
+    let (height, width) = (last.pixels.len(), last.pixels[0].len());
+
+    fs::create_dir_all(dir)?;
+    for (round, image) in images.iter().enumerate() {
+        let path = Path::new(dir).join(format!("round-{:02}.pbm", round));
+        fs::write(path, image.to_pbm(origin, height, width))?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const IMAGE: &[&[u8]] = &[
-        &[b'#', b'.', b'.', b'#', b'.'],
-        &[b'#', b'.', b'.', b'.', b'.'],
-        &[b'#', b'#', b'.', b'.', b'#'],
-        &[b'.', b'.', b'#', b'.', b'.'],
-        &[b'.', b'.', b'#', b'#', b'#'],
-    ];
+    const IMAGE: &[&[u8]] = &[b"#..#.", b"#....", b"##..#", b"..#..", b"..###"];
     const ENHANCEMENT: [u8; 512] = [
         b'.', b'.', b'#', b'.', b'#', b'.', b'.', b'#', b'#', b'#', b'#', b'#', b'.', b'#', b'.',
         b'#', b'.', b'#', b'.', b'#', b'#', b'#', b'.', b'#', b'#', b'.', b'.', b'.', b'.', b'.',
@@ -204,11 +323,80 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(Image::enhance(IMAGE, ENHANCEMENT, 2).lit_pixels(), 35);
+        let image = InfiniteImage::enhance_n(IMAGE, &ENHANCEMENT, 2);
+        assert_eq!(image.lit_pixels(), Ok(35));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(Image::enhance(IMAGE, ENHANCEMENT, 50).lit_pixels(), 3351);
+        let image = InfiniteImage::enhance_n(IMAGE, &ENHANCEMENT, 50);
+        assert_eq!(image.lit_pixels(), Ok(3351));
+    }
+
+    #[test]
+    fn display_and_export() {
+        let image = InfiniteImage::enhance_n(IMAGE, &ENHANCEMENT, 1);
+        assert_eq!(
+            image.to_string(),
+            ".##.##.\n#..#.#.\n##.#..#\n####..#\n.#..##.\n..##..#\n...#.#.\n"
+        );
+
+        let original = InfiniteImage::new(IMAGE);
+        assert_eq!(original.to_string().replace('\n', "").len(), 25);
+        assert_eq!(
+            original.to_pbm((-1, -1), 3, 4),
+            "P1\n4 3\n0 0 0 0\n0 1 0 0\n0 1 0 0\n"
+        );
+
+        let rounds = enhancement_rounds(IMAGE, &ENHANCEMENT, 2);
+        assert_eq!(rounds.len(), 3);
+        assert_eq!(rounds[2], InfiniteImage::enhance_n(IMAGE, &ENHANCEMENT, 2));
+    }
+
+    #[test]
+    fn double_buffered() {
+        for rounds in [0, 1, 2, 5] {
+            let by_round = (0..rounds).fold(InfiniteImage::new(IMAGE), |image, _| {
+                image.enhance(&ENHANCEMENT)
+            });
+            assert_eq!(
+                InfiniteImage::enhance_n(IMAGE, &ENHANCEMENT, rounds),
+                by_round
+            );
+        }
+
+        let mut flashing = ENHANCEMENT;
+        flashing[0] = b'#';
+        flashing[511] = b'.';
+        let by_round = (0..3).fold(InfiniteImage::new(IMAGE), |image, _| {
+            image.enhance(&flashing)
+        });
+        assert_eq!(InfiniteImage::enhance_n(IMAGE, &flashing, 3), by_round);
+    }
+
+    #[test]
+    fn background() {
+        let with_ends = |first, last| {
+            let mut enhancement = ENHANCEMENT;
+            enhancement[0] = first;
+            enhancement[511] = last;
+            enhancement
+        };
+
+        let dark = InfiniteImage::enhance_n(IMAGE, &with_ends(b'.', b'#'), 3);
+        assert_eq!(dark.background, 0);
+        assert!(dark.lit_pixels().is_ok());
+
+        let flashing = with_ends(b'#', b'.');
+        assert_eq!(InfiniteImage::enhance_n(IMAGE, &flashing, 1).background, 1);
+        assert_eq!(
+            InfiniteImage::enhance_n(IMAGE, &flashing, 1).lit_pixels(),
+            Err("Infinitely many pixels are lit")
+        );
+        assert_eq!(InfiniteImage::enhance_n(IMAGE, &flashing, 2).background, 0);
+
+        let lit = InfiniteImage::enhance_n(IMAGE, &with_ends(b'#', b'#'), 4);
+        assert_eq!(lit.background, 1);
+        assert!(lit.lit_pixels().is_err());
     }
 }
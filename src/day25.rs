@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use once_cell::sync::Lazy;
 
 use crate::utils;
@@ -8,55 +11,132 @@ const INPUT_PATH: &str = "input/day25";
 
 type Location = (usize, usize);
 
-fn steps_until_no_movement(mut cucumbers: Vec<Vec<u8>>) -> usize {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    East,
+    South,
+    West,
+    North,
+}
+
+impl Direction {
+    // The sea floor wraps around on every side.
+    fn step(self, (i, j): Location, (height, width): (usize, usize)) -> Location {
+        match self {
+            Direction::East => (i, (j + 1) % width),
+            Direction::South => ((i + 1) % height, j),
+            Direction::West => (i, (j + width - 1) % width),
+            Direction::North => ((i + height - 1) % height, j),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let direction = match s {
+            "east" => Direction::East,
+            "south" => Direction::South,
+            "west" => Direction::West,
+            "north" => Direction::North,
+            _ => return Err("Not a direction"),
+        };
+
+        Ok(direction)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Herd {
+    symbol: u8,
+    direction: Direction,
+}
+
+// Parses herds written as `<symbol>:<direction>`, e.g. `>:east`.
+impl FromStr for Herd {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (symbol, direction) = s.split_once(':').ok_or("Missing ':'")?;
+        let symbol = match symbol.as_bytes() {
+            [b'.'] => return Err("'.' is reserved for empty locations"),
+            &[symbol] => symbol,
+            _ => return Err("A herd's symbol must be a single character"),
+        };
+
+        Ok(Herd {
+            symbol,
+            direction: direction.parse()?,
+        })
+    }
+}
+
+const HERDS: &[Herd] = &[
+    Herd {
+        symbol: b'>',
+        direction: Direction::East,
+    },
+    Herd {
+        symbol: b'v',
+        direction: Direction::South,
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    // Number of steps up to and including the first one where nothing moved.
+    Stopped(usize),
+    // The sea floor after `first_repeat` steps is the same as `period` steps earlier.
+    Cycle { first_repeat: usize, period: usize },
+}
+
+// Herds move one after the other, in the given order, at every step.
+fn simulate(mut cucumbers: Vec<Vec<u8>>, herds: &[Herd]) -> Outcome {
     let mut container = Vec::with_capacity(128);
-    let mut moved;
+    let mut seen = HashMap::new();
     let mut count = 0;
 
     loop {
-        move_direction(&cucumbers, b'>', &mut container);
-        update(&mut cucumbers, &container);
-        moved = !container.is_empty();
-        container.clear();
-        move_direction(&cucumbers, b'v', &mut container);
-        update(&mut cucumbers, &container);
-        moved = moved || !container.is_empty();
-        container.clear();
+        seen.insert(cucumbers.clone(), count);
+
+        let mut moved = false;
+        for &herd in herds {
+            move_herd(&cucumbers, herd, &mut container);
+            update(&mut cucumbers, &container);
+            moved = moved || !container.is_empty();
+            container.clear();
+        }
 
         count += 1;
         if !moved {
-            break;
+            return Outcome::Stopped(count);
+        }
+        if let Some(&previous) = seen.get(&cucumbers) {
+            return Outcome::Cycle {
+                first_repeat: count,
+                period: count - previous,
+            };
         }
     }
-
-    count
 }
 
-fn update(cucumbers: &mut Vec<Vec<u8>>, container: &[(Location, Location)]) {
+fn update(cucumbers: &mut [Vec<u8>], container: &[(Location, Location)]) {
     for &((i, j), (new_i, new_j)) in container {
         cucumbers[new_i][new_j] = cucumbers[i][j];
         cucumbers[i][j] = b'.';
     }
 }
 
-fn move_direction(cucumbers: &[Vec<u8>], direction: u8, container: &mut Vec<(Location, Location)>) {
-    for i in 0..cucumbers.len() {
-        for j in 0..cucumbers[0].len() {
-            if cucumbers[i][j] == direction {
-                match direction {
-                    b'>' => {
-                        let new_location = (i, (j + 1) % cucumbers[0].len());
-                        if cucumbers[i][new_location.1] == b'.' {
-                            container.push(((i, j), new_location));
-                        }
-                    }
-                    b'v' => {
-                        let new_location = ((i + 1) % cucumbers.len(), j);
-                        if cucumbers[new_location.0][j] == b'.' {
-                            container.push(((i, j), new_location));
-                        }
-                    }
-                    _ => unreachable!(),
+fn move_herd(cucumbers: &[Vec<u8>], herd: Herd, container: &mut Vec<(Location, Location)>) {
+    let size = (cucumbers.len(), cucumbers[0].len());
+
+    for (i, row) in cucumbers.iter().enumerate() {
+        for (j, &cucumber) in row.iter().enumerate() {
+            if cucumber == herd.symbol {
+                let (new_i, new_j) = herd.direction.step((i, j), size);
+                if cucumbers[new_i][new_j] == b'.' {
+                    container.push(((i, j), (new_i, new_j)));
                 }
             }
         }
@@ -64,7 +144,41 @@ fn move_direction(cucumbers: &[Vec<u8>], direction: u8, container: &mut Vec<(Loc
 }
 
 pub fn part1() -> usize {
-    steps_until_no_movement(INPUT.clone())
+    match simulate(INPUT.clone(), HERDS) {
+        Outcome::Stopped(count) => count,
+        Outcome::Cycle { .. } => panic!("Sea cucumbers never stop moving"),
+    }
+}
+
+// Without any herds, the puzzle's east and south herds are simulated.
+pub fn print_simulation(herds: &[&str]) -> Result<(), &'static str> {
+    let mut herds = herds
+        .iter()
+        .map(|herd| herd.parse())
+        .collect::<Result<Vec<Herd>, _>>()?;
+    for (i, herd) in herds.iter().enumerate() {
+        if herds[..i].iter().any(|other| other.symbol == herd.symbol) {
+            return Err("Herds must have distinct symbols");
+        }
+    }
+    if herds.is_empty() {
+        herds = HERDS.to_vec();
+    }
+
+    match simulate(INPUT.clone(), &herds) {
+        Outcome::Stopped(count) => println!("Stopped after {} steps", count),
+        Outcome::Cycle {
+            first_repeat,
+            period,
+        } => println!(
+            "Step {} repeats step {} (period {})",
+            first_repeat,
+            first_repeat - period,
+            period
+        ),
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -85,8 +199,60 @@ mod tests {
         ]
     });
 
+    fn grid(rows: &[&str]) -> Vec<Vec<u8>> {
+        rows.iter().map(|row| row.bytes().collect()).collect()
+    }
+
     #[test]
     fn part1_example() {
-        assert_eq!(steps_until_no_movement(INPUT.clone()), 58);
+        assert_eq!(simulate(INPUT.clone(), HERDS), Outcome::Stopped(58));
+    }
+
+    #[test]
+    fn cycles() {
+        assert_eq!(
+            simulate(grid(&[">."]), HERDS),
+            Outcome::Cycle {
+                first_repeat: 2,
+                period: 2
+            }
+        );
+        assert_eq!(
+            simulate(grid(&["..>.", "....", "...."]), HERDS),
+            Outcome::Cycle {
+                first_repeat: 4,
+                period: 4
+            }
+        );
+        assert_eq!(simulate(grid(&[">>", "vv"]), HERDS), Outcome::Stopped(1));
+    }
+
+    #[test]
+    fn custom_herds() {
+        let herds: Vec<Herd> = ["<:west", "^:north", ">:east"]
+            .iter()
+            .map(|herd| herd.parse().unwrap())
+            .collect();
+
+        // `<` moves first, so `>` is blocked until it wraps around.
+        let mut cucumbers = grid(&["..<>.", ".....", "..^.."]);
+        let mut container = Vec::new();
+        move_herd(&cucumbers, herds[0], &mut container);
+        update(&mut cucumbers, &container);
+        assert_eq!(cucumbers, grid(&[".<.>.", ".....", "..^.."]));
+
+        assert_eq!(
+            simulate(grid(&["^", "."]), &herds),
+            Outcome::Cycle {
+                first_repeat: 2,
+                period: 2
+            }
+        );
+
+        assert_eq!("x:up".parse::<Herd>(), Err("Not a direction"));
+        assert_eq!(
+            ".:east".parse::<Herd>(),
+            Err("'.' is reserved for empty locations")
+        );
     }
 }
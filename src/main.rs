@@ -1,3 +1,7 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
 mod day01;
 mod day02;
 mod day03;
@@ -19,6 +23,94 @@ mod day25;
 mod utils;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => run_all(),
+        ["day08", "bench"] => day08::bench_part2(10),
+        ["day08", "show"] => day08::print_decoded(),
+        ["day08", "wiring"] => day08::print_wirings(),
+        ["day09", "basins"] => day09::print_basins(),
+        ["day09", "show"] => day09::print_heightmap(),
+        ["day09", "ppm", path] => day09::export_heightmap(path).unwrap(),
+        ["day10", "report", brackets @ ..] => or_exit(day10::print_report(brackets)),
+        ["day11", "--animate"] => day11::animate(Duration::from_millis(100)),
+        ["day11", "--animate", delay] => or_exit(
+            parse_arg(delay, "DELAY_MS must be a whole number of milliseconds")
+                .map(|delay| day11::animate(Duration::from_millis(delay))),
+        ),
+        ["day12", "dot"] => print!("{}", day12::dot(false)),
+        ["day12", "dot", "--traversals"] => print!("{}", day12::dot(true)),
+        ["day13", "folds"] => or_exit(day13::print_folds()),
+        ["day13", "folds", "--svg", dir] => or_exit(day13::export_folds_svg(dir)),
+        ["day14", "polymer", n] => or_exit(
+            parse_arg(n, STEPS_ERROR)
+                .map_err(String::from)
+                .and_then(day14::print_polymer),
+        ),
+        ["day14", "counts", n] => {
+            or_exit(parse_arg(n, STEPS_ERROR).and_then(|n| day14::print_element_counts(n, None)))
+        }
+        ["day14", "counts", n, "--mod", m] => or_exit(parse_arg(n, STEPS_ERROR).and_then(|n| {
+            let m = parse_arg(m, "M must be a positive whole number")?;
+            day14::print_element_counts(n, Some(m))
+        })),
+        ["day18", "trace", lhs, rhs] => or_exit(day18::print_reduction(lhs, rhs)),
+        ["day18", "bench"] => day18::bench_part2(10),
+        ["day18", "largest"] => day18::print_largest_sum(),
+        ["day20", "bench"] => day20::bench_part2(10),
+        ["day20", "show", rounds] => {
+            or_exit(parse_arg(rounds, ROUNDS_ERROR).map(day20::print_image))
+        }
+        ["day20", "export", dir] => day20::export_rounds(dir, 50).unwrap(),
+        ["day20", "export", dir, rounds] => or_exit(
+            parse_arg(rounds, ROUNDS_ERROR)
+                .map(|rounds| day20::export_rounds(dir, rounds).unwrap()),
+        ),
+        ["day25", "simulate", herds @ ..] => or_exit(day25::print_simulation(herds)),
+        _ => {
+            eprintln!("Usage: advent-of-code-2021 [day08 bench]");
+            eprintln!("       advent-of-code-2021 [day08 show]");
+            eprintln!("       advent-of-code-2021 [day08 wiring]");
+            eprintln!("       advent-of-code-2021 [day09 basins]");
+            eprintln!("       advent-of-code-2021 [day09 show]");
+            eprintln!("       advent-of-code-2021 [day09 ppm PATH]");
+            eprintln!("       advent-of-code-2021 [day10 report [OC:CORRUPTION:COMPLETION...]]");
+            eprintln!("       advent-of-code-2021 [day11 --animate [DELAY_MS]]");
+            eprintln!("       advent-of-code-2021 [day12 dot [--traversals]]");
+            eprintln!("       advent-of-code-2021 [day13 folds [--svg DIR]]");
+            eprintln!("       advent-of-code-2021 [day14 polymer N]");
+            eprintln!("       advent-of-code-2021 [day14 counts N [--mod M]]");
+            eprintln!("           (N above about 125 overflows without --mod)");
+            eprintln!("       advent-of-code-2021 [day18 trace LHS RHS]");
+            eprintln!("       advent-of-code-2021 [day18 bench]");
+            eprintln!("       advent-of-code-2021 [day18 largest]");
+            eprintln!("       advent-of-code-2021 [day20 bench]");
+            eprintln!("       advent-of-code-2021 [day20 show ROUNDS]");
+            eprintln!("       advent-of-code-2021 [day20 export DIR [ROUNDS]]");
+            eprintln!("       advent-of-code-2021 [day25 simulate SYMBOL:DIRECTION...]");
+            std::process::exit(1);
+        }
+    }
+}
+
+const STEPS_ERROR: &str = "N must be a whole number of steps";
+const ROUNDS_ERROR: &str = "ROUNDS must be a whole number of rounds";
+
+fn parse_arg<T: FromStr>(arg: &str, error: &'static str) -> Result<T, &'static str> {
+    arg.parse().map_err(|_| error)
+}
+
+// Reports invalid input the same way as invalid arguments, without a panic.
+fn or_exit<E: Display>(result: Result<(), E>) {
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run_all() {
     println!("Day 01 [part 01]: {}", day01::part1());
     println!("Day 01 [part 02]: {}\n", day01::part2());
 
@@ -56,9 +148,7 @@ fn main() {
     println!("Day 12 [part 02]: {}\n", day12::part2());
 
     println!("Day 13 [part 01]: {}", day13::part1());
-    println!("Day 13 [part 01]:");
-    day13::part2();
-    println!();
+    println!("Day 13 [part 02]: {}\n", day13::part2());
 
     println!("Day 14 [part 01]: {}", day14::part1());
     println!("Day 14 [part 02]: {}\n", day14::part2());